@@ -0,0 +1,39 @@
+//! Driving a module from a `no_std` async executor.
+//!
+//! Only the `embedded-io-async` traits are used, so this builds for any target. On hardware the
+//! serial halves come from the HAL, for example splitting an embassy `BufferedUart` on an nRF52
+//! or STM32, and the two tasks are spawned with `#[embassy_executor::task]`. The storage must be
+//! `'static` as the ingress runs in its own task, a `StaticCell` provides it without `unsafe`.
+
+#![no_std]
+
+use core::time::Duration;
+use embedded_io_async::{Read, Write};
+use wurth_telesto::{Ingress, Radio, RadioStorage, Timer};
+
+/// Set up the radio, returning the ingress to run in its own task.
+pub fn init<W: Write, R: Read, T: Timer>(
+    tx: W,
+    rx: R,
+    timer: T,
+    storage: &'static mut RadioStorage,
+) -> (Radio<'static, W, T>, Ingress<'static, R>) {
+    let (radio, ingress) = Radio::from_storage(tx, rx, storage);
+
+    (radio.with_timer(timer), ingress)
+}
+
+/// Receive task, dispatching frames to the radio until the serial port is closed or fails.
+pub async fn ingest_task<R: Read>(mut ingress: Ingress<'static, R>) {
+    ingress.run().await;
+}
+
+/// Send task, transmitting a packet to the configured destination every second.
+pub async fn send_task<W: Write, T: Timer>(mut radio: Radio<'static, W, T>, mut timer: T) -> ! {
+    loop {
+        // a failed send is retried on the next tick.
+        let _ = radio.send(b"hello").await;
+
+        timer.delay(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,185 @@
+//! Frame reassembly from arbitrarily sized reads.
+
+use heapless::Vec;
+
+use crate::command::{ChecksumState, DecodeError, MAX_PAYLOAD_LEN, START};
+use crate::{buffer, Buffer, IngestError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    Command,
+    Length,
+    Payload,
+    Checksum,
+}
+
+/// Incremental frame parser, fed bytes one at a time as they arrive.
+pub(crate) struct FrameAssembler {
+    state: State,
+    command: u8,
+    len: usize,
+    payload: Vec<u8, MAX_PAYLOAD_LEN>,
+    checksum: ChecksumState,
+}
+
+impl FrameAssembler {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: State::Start,
+            command: 0,
+            len: 0,
+            payload: Vec::new(),
+            checksum: ChecksumState::new(),
+        }
+    }
+
+    /// Returns true if no frame is partially received.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.state == State::Start
+    }
+
+    /// Discard any partially received frame.
+    pub(crate) fn reset(&mut self) {
+        self.state = State::Start;
+        self.payload.clear();
+        self.checksum = ChecksumState::new();
+    }
+
+    /// Add a received byte, returning the command and payload once a frame is complete.
+    ///
+    /// Bytes outside a frame are skipped until the next start byte. Frames with an invalid
+    /// checksum are discarded, returning a [`DecodeError::Checksum`].
+    pub(crate) fn push<IO>(&mut self, byte: u8) -> Option<Result<(u8, Buffer), IngestError<IO>>> {
+        if self.state != State::Checksum {
+            self.checksum.update(&[byte]);
+        }
+
+        match self.state {
+            State::Start if byte == START => self.state = State::Command,
+            State::Start => self.reset(),
+            State::Command => {
+                self.command = byte;
+                self.state = State::Length;
+            }
+            State::Length => {
+                self.len = byte as usize;
+                if self.len > MAX_PAYLOAD_LEN {
+                    self.reset();
+                    return Some(Err(IngestError::Decode(DecodeError::PayloadLength)));
+                }
+
+                self.state = if self.len == 0 {
+                    State::Checksum
+                } else {
+                    State::Payload
+                };
+            }
+            State::Payload => {
+                // length was checked against the capacity, so this can't fail.
+                self.payload.push(byte).ok();
+                if self.payload.len() == self.len {
+                    self.state = State::Checksum;
+                }
+            }
+            State::Checksum => {
+                let frame = if self.checksum.finish() == byte {
+                    Ok((self.command, buffer(&self.payload)))
+                } else {
+                    Err(IngestError::Decode(DecodeError::Checksum))
+                };
+                self.reset();
+
+                return Some(frame);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{command, MAX_FRAME_LEN};
+    use core::convert::Infallible;
+
+    fn push_all(
+        assembler: &mut FrameAssembler,
+        bytes: &[u8],
+    ) -> Option<Result<(u8, Buffer), IngestError<Infallible>>> {
+        bytes.iter().find_map(|&byte| assembler.push(byte))
+    }
+
+    #[test]
+    fn assemble_split_frame() {
+        let mut assembler = FrameAssembler::new();
+
+        assert!(push_all(&mut assembler, &[0x00, START, 0x81]).is_none());
+        assert!(!assembler.is_idle());
+
+        let (command, payload) = push_all(&mut assembler, &[0x01, 0x48, 0xCA])
+            .unwrap()
+            .unwrap();
+        assert_eq!(command, 0x81);
+        assert_eq!(&payload, &[0x48]);
+        assert!(assembler.is_idle());
+    }
+
+    #[test]
+    fn assemble_bad_checksum() {
+        let mut assembler = FrameAssembler::new();
+
+        assert!(matches!(
+            push_all(&mut assembler, &[START, 0x81, 0x00, 0xFF]),
+            Some(Err(IngestError::Decode(DecodeError::Checksum)))
+        ));
+        assert!(assembler.is_idle());
+    }
+
+    #[test]
+    fn assemble_payload_len_boundary() {
+        let mut assembler = FrameAssembler::new();
+        let mut raw = [0; MAX_FRAME_LEN];
+        let len = command(&mut raw, 0x81, &[0xAB; MAX_PAYLOAD_LEN]);
+
+        let (_, payload) = push_all(&mut assembler, &raw[..len]).unwrap().unwrap();
+        assert_eq!(payload.len(), MAX_PAYLOAD_LEN);
+
+        let oversized = [START, 0x81, MAX_PAYLOAD_LEN as u8 + 1];
+        assert!(matches!(
+            push_all(&mut assembler, &oversized),
+            Some(Err(IngestError::Decode(DecodeError::PayloadLength)))
+        ));
+        assert!(assembler.is_idle());
+    }
+}
+
+#[cfg(all(test, feature = "proptest"))]
+mod proptests {
+    use super::*;
+    use crate::command::{command, MAX_FRAME_LEN};
+    use core::convert::Infallible;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn assemble_garbage_then_frame(garbage in vec(any::<u8>(), 0..4 * MAX_FRAME_LEN)) {
+            let mut assembler = FrameAssembler::new();
+            let mut raw = [0; MAX_FRAME_LEN];
+            let len = command(&mut raw, 0x81, &[0x48, 0xC4]);
+
+            for byte in garbage {
+                assembler.push::<Infallible>(byte);
+            }
+
+            // garbage mustn't panic or wedge the assembler, a frame is still recovered afterwards.
+            assembler.reset();
+            let frame = raw[..len].iter().find_map(|&byte| assembler.push::<Infallible>(byte));
+            let (command, payload) = frame.unwrap().unwrap();
+            prop_assert_eq!(command, 0x81);
+            prop_assert_eq!(&payload[..], &[0x48, 0xC4]);
+        }
+    }
+}
@@ -1,8 +1,19 @@
+mod monitor;
+mod provision;
+
 use clap::{Parser, Subcommand};
-use heapless::spsc::Queue;
-use std::ptr::addr_of_mut;
+use embedded_io_async::Write;
+use std::fmt::Debug;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
 use tokio_serial::SerialPortBuilderExt;
-use wurth_telesto::{Event, Mode, Radio};
+use wurth_telesto::tokio::TokioTimer;
+use wurth_telesto::{
+    rssi_dbm, Channel, CommandError, Event, Hex, IngestError, Mode, Radio, RadioStorage, Setting,
+    Timer, MAX_DATA_LEN,
+};
 
 #[derive(Parser)]
 pub struct Cli {
@@ -13,24 +24,49 @@ pub struct Cli {
     /// Baud rate.
     #[arg(default_value_t = 115200)]
     baud: u32,
+    /// Time to wait for each response (ms), defaults to a suitable time for each command.
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Send data to configured address.
     ///
-    /// You may send data using escaped strings such as \uXXXX and \xNN.
-    Send { data: String },
+    /// You may send data using escaped strings such as \uXXXX and \xNN. Data longer than a
+    /// single packet is split over several.
+    Send {
+        /// Data to send.
+        #[arg(required_unless_present = "file")]
+        data: Option<String>,
+        /// Interpret the data as hex bytes, such as "48 65 6c 6c 6f".
+        #[arg(long)]
+        hex: bool,
+        /// Send the contents of a file, or stdin if "-".
+        #[arg(long, conflicts_with_all = ["data", "hex"])]
+        file: Option<PathBuf>,
+    },
     /// Reset module.
     Reset,
     /// Shutdown module.
     Shutdown,
     /// Echo incomming data sent to the configured address.
-    Echo,
+    Echo {
+        /// Only respond to packets received with at least this RSSI (dBm).
+        #[arg(long, allow_negative_numbers = true)]
+        min_rssi: Option<i8>,
+    },
     /// Enter standby.
     Standby,
     /// Receive signal strength of last received packet.
-    Rssi,
+    Rssi {
+        /// Keep querying the signal strength.
+        #[arg(long)]
+        watch: bool,
+        /// Time between queries when watching (ms).
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
     /// Transmit power.
     TxPower { power: u8 },
     /// Set channel.
@@ -41,69 +77,480 @@ enum Commands {
     DestAddr { address: u8 },
     /// Operating mode.
     Mode { mode: Mode },
+    /// Listen on each channel in turn, charting the strongest signal received on each.
+    Scan {
+        /// First channel to scan.
+        #[arg(long, default_value_t = Channel::MIN)]
+        from: u8,
+        /// Last channel to scan.
+        #[arg(long, default_value_t = Channel::MAX)]
+        to: u8,
+        /// Time to listen on each channel (ms).
+        #[arg(long, default_value_t = 1000)]
+        dwell: u64,
+    },
+    /// Periodically print link statistics gathered from received events.
+    Monitor {
+        /// Time between reports (ms).
+        #[arg(long, default_value_t = 1000)]
+        interval: u64,
+        /// Data to send before each report, reporting whether the send succeeded.
+        #[arg(long)]
+        send: Option<String>,
+    },
+    /// Apply user settings from a configuration file.
+    Provision {
+        file: PathBuf,
+        /// Print the settings which would be applied without sending them.
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
+/// Receive errors seen by the ingest task, reported by the monitor.
+static RECEIVE_ERRORS: AtomicU32 = AtomicU32::new(0);
+
 #[tokio::main]
 async fn main() {
-    let args = Cli::parse();
+    if let Err(err) = run(Cli::parse()).await {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+
+    println!("Finished...");
+}
+
+/// Describe a command error for the user, including the serial port error.
+fn describe<IO: Debug>(err: CommandError<IO>) -> String {
+    match err {
+        CommandError::Io(err) => format!("serial port error ({:?})", err),
+        err => err.to_string(),
+    }
+}
+
+/// Parse hex bytes, optionally separated by whitespace.
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex data must have two digits per byte".into());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("invalid hex byte \"{}\"", pair))
+        })
+        .collect()
+}
+
+/// Data to send, from the argument or the file given to [`Commands::Send`].
+fn send_data(data: Option<String>, hex: bool, file: Option<PathBuf>) -> Result<Vec<u8>, String> {
+    match (data, file) {
+        (_, Some(file)) if file.as_os_str() == "-" => {
+            let mut data = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut data)
+                .map_err(|err| format!("unable to read stdin: {}", err))?;
+            Ok(data)
+        }
+        (_, Some(file)) => std::fs::read(&file)
+            .map_err(|err| format!("unable to read {}: {}", file.display(), err)),
+        (Some(data), None) if hex => parse_hex(&data),
+        (Some(data), None) => unescape::unescape(&data)
+            .map(String::into_bytes)
+            .ok_or_else(|| "invalid escape sequence".to_string()),
+        (None, None) => Err("no data to send".into()),
+    }
+}
+
+/// Print a bar for the signal strength on a channel, scaled from -120dBm to -20dBm.
+fn print_scan(channel: Channel, rssi: Option<i8>) {
+    match rssi {
+        Some(rssi) => {
+            let len = (rssi as i32 + 120).clamp(0, 100) as usize / 2;
+            println!("{:3} | {} {}dBm", channel.get(), "#".repeat(len), rssi);
+        }
+        None => println!("{:3} |", channel.get()),
+    }
+}
+
+fn print_rssi(rssi: Option<i8>) {
+    match rssi {
+        Some(rssi) => println!("RSSI: {}dBm", rssi),
+        None => println!("No RSSI available"),
+    }
+}
+
+async fn run(args: Cli) -> Result<(), String> {
+    let provision = match &args.subcommand {
+        Commands::Provision { file, dry_run } => {
+            let text = std::fs::read_to_string(file)
+                .map_err(|err| format!("unable to read {}: {}", file.display(), err))?;
+            let settings =
+                provision::parse(&text).map_err(|err| format!("invalid configuration: {}", err))?;
 
-    let stream = tokio_serial::new(args.port, args.baud)
+            if *dry_run {
+                for (setting, value) in settings {
+                    println!("Would set {:?} to {}", setting, value);
+                }
+                return Ok(());
+            }
+
+            settings
+        }
+        _ => Vec::new(),
+    };
+
+    let stream = tokio_serial::new(&args.port, args.baud)
         .data_bits(tokio_serial::DataBits::Eight)
         .stop_bits(tokio_serial::StopBits::One)
         .parity(tokio_serial::Parity::None)
         .open_native_async()
-        .unwrap();
+        .map_err(|err| format!("unable to open {}: {}", args.port, err))?;
 
     let (tx, rx) = wurth_telesto::tokio::split_stream(stream);
 
-    let mut queue_response = Queue::new();
-    let mut queue_event = Queue::new();
+    // the ingress runs in a spawned task, so the storage must live for the whole program.
+    let storage = Box::leak(Box::new(RadioStorage::new()));
+    let (radio, ingress) = Radio::from_storage(tx, rx, storage);
+
+    let mut radio = radio.with_timer(TokioTimer);
+    if let Some(timeout) = args.timeout {
+        radio = radio.with_default_response_timeout(Duration::from_millis(timeout));
+    }
 
-    let (mut radio, mut ingress) = Radio::new(
-        tx,
-        rx,
-        unsafe { addr_of_mut!(queue_response).as_mut().unwrap() },
-        unsafe { addr_of_mut!(queue_event).as_mut().unwrap() },
-    );
+    let mut ingress = ingress.with_timeout(TokioTimer, Duration::from_millis(100));
 
     tokio::task::spawn(async move {
-        ingress.ingest().await.unwrap();
+        loop {
+            match ingress.ingest().await {
+                Err(IngestError::Closed) => {
+                    eprintln!("Serial port closed.");
+                    std::process::exit(1);
+                }
+                Err(IngestError::Io(err)) => {
+                    eprintln!("Serial port error: {}", err);
+                    std::process::exit(1);
+                }
+                // not an error, the received data isn't framed until the module is back in
+                // command mode.
+                Err(IngestError::Transparent) => ingress.wait_for_command_mode().await,
+                Err(err) => {
+                    RECEIVE_ERRORS.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("Receive error: {}", err);
+                }
+                Ok(()) => {}
+            }
+        }
     });
 
-    match args.subcommand {
-        Commands::Send { data } => {
-            let output = unescape::unescape(&data).unwrap();
-            radio.send(output.as_bytes()).await.unwrap()
+    execute(&mut radio, args.subcommand, provision).await
+}
+
+/// Run a subcommand against the radio.
+///
+/// `provision` holds the settings parsed for [`Commands::Provision`].
+async fn execute<W, T>(
+    radio: &mut Radio<'_, W, T>,
+    command: Commands,
+    provision: Vec<(Setting, u8)>,
+) -> Result<(), String>
+where
+    W: Write,
+    W::Error: Debug,
+    T: Timer,
+{
+    match command {
+        Commands::Send { data, hex, file } => {
+            let data = send_data(data, hex, file)?;
+            for chunk in data.chunks(MAX_DATA_LEN) {
+                radio.send(chunk).await.map_err(describe)?;
+            }
         }
-        Commands::Reset => radio.reset().await.unwrap(),
-        Commands::Echo => loop {
+        Commands::Reset => radio.reset().await.map_err(describe)?,
+        Commands::Shutdown => radio.shutdown().await.map_err(describe)?,
+        Commands::Echo { min_rssi } => loop {
             let event = radio.poll_event().await;
             if let Event::DataReceived = event.command() {
-                let data = &event.data()[0..event.data().len() - 1];
-                let strength = *event.data().last().unwrap() as i8;
+                let Some(strength) = event.rssi() else {
+                    continue;
+                };
+                let data = &event.data()[..event.data().len() - 1];
+
+                println!("Got data: {} with RSSI: {}dBm", Hex(data), strength);
 
-                println!("Got data: {:#02x?} with RSSI: {}dBm", data, strength);
-                radio.send(event.data()).await.unwrap();
+                if min_rssi.is_some_and(|min| strength < min) {
+                    println!("Below RSSI threshold, not responding.");
+                    continue;
+                }
+
+                // received frames can be longer than a single send allows.
+                for chunk in event.data().chunks(MAX_DATA_LEN) {
+                    radio.send(chunk).await.map_err(describe)?;
+                }
                 println!("Sent response.");
             }
         },
-        Commands::Standby => radio.standby().await.unwrap(),
-        Commands::Rssi => {
-            let rssi = radio.rssi().await.unwrap();
-            if rssi == 0x80 {
-                println!("No RSSI available");
-            } else {
-                let rssi = rssi as i8;
-                println!("RSSI: {}dBm", rssi);
+        Commands::Standby => radio.standby().await.map_err(describe)?,
+        Commands::Rssi {
+            watch: true,
+            interval,
+        } => radio
+            .rssi_continuous(Duration::from_millis(interval), print_rssi)
+            .await
+            .map_err(describe)?,
+        Commands::Rssi { watch: false, .. } => {
+            let rssi = radio.rssi().await.map_err(describe)?;
+            print_rssi(rssi_dbm(rssi));
+        }
+        Commands::TxPower { power } => radio.tx_power(power).await.map_err(describe)?,
+        Commands::Channel { channel } => {
+            let channel = provision::parse_channel(channel)?;
+            radio.channel(channel).await.map_err(describe)?
+        }
+        Commands::DestNet { id } => radio.destination_net(id).await.map_err(describe)?,
+        Commands::DestAddr { address } => {
+            radio.destination_address(address).await.map_err(describe)?
+        }
+        Commands::Mode { mode } => radio.mode(mode).await.map_err(describe)?,
+        Commands::Scan { from, to, dwell } => {
+            let results = radio
+                .channel_scan(from..=to, Duration::from_millis(dwell))
+                .await
+                .map_err(describe)?;
+            for (channel, rssi) in results {
+                print_scan(channel, rssi);
+            }
+        }
+        Commands::Monitor { interval, send } => {
+            let interval = Duration::from_millis(interval);
+            let send = send.map(String::into_bytes);
+            if send.as_ref().is_some_and(|data| data.len() > MAX_DATA_LEN) {
+                return Err(format!(
+                    "data to send must be at most {} bytes",
+                    MAX_DATA_LEN
+                ));
+            }
+
+            let mut stats = monitor::Stats::default();
+            let mut start = tokio::time::Instant::now();
+
+            loop {
+                tokio::select! {
+                    event = radio.poll_event() => stats.record(&event),
+                    _ = tokio::time::sleep_until(start + interval) => {
+                        if let Some(data) = &send {
+                            stats.record_send(radio.send(data).await.map_err(describe));
+                        }
+
+                        let errors = RECEIVE_ERRORS.swap(0, Ordering::Relaxed);
+                        let dropped = radio.dropped_frames().unwrap_or(0);
+                        println!("{}", stats.report(start.elapsed(), errors, dropped));
+                        start = tokio::time::Instant::now();
+                    }
+                }
+            }
+        }
+        Commands::Provision { .. } => {
+            for (setting, value) in provision {
+                match radio.set_user_setting(setting, &[value]).await {
+                    Ok(()) => println!("{:?} = {}: ok", setting, value),
+                    Err(err) => println!("{:?} = {}: failed ({})", setting, value, describe(err)),
+                }
             }
         }
-        Commands::TxPower { power } => radio.tx_power(power).await.unwrap(),
-        Commands::Channel { channel } => radio.channel(channel).await.unwrap(),
-        Commands::DestNet { id } => radio.destination_net(id).await.unwrap(),
-        Commands::DestAddr { address } => radio.destination_address(address).await.unwrap(),
-        Commands::Mode { mode } => radio.mode(mode).await.unwrap(),
-        _ => todo!(),
     }
 
-    println!("Finished...");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::future::poll_fn;
+    use std::rc::Rc;
+    use std::task::Poll;
+    use wurth_telesto::{checksum, Mode};
+
+    /// In-memory module, replying to each request with a successful response.
+    #[derive(Default)]
+    struct Module {
+        requests: Vec<(u8, Vec<u8>)>,
+        replies: VecDeque<u8>,
+    }
+
+    #[derive(Clone, Default)]
+    struct MockModule(Rc<RefCell<Module>>);
+
+    impl embedded_io_async::ErrorType for MockModule {
+        type Error = std::convert::Infallible;
+    }
+
+    impl Write for MockModule {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            let mut module = self.0.borrow_mut();
+            let (cmd, data) = (buf[1], buf[3..3 + buf[2] as usize].to_vec());
+
+            let payload = match cmd {
+                // rssi replies with the signal strength, set channel echoes the channel.
+                0x0D => vec![0xC4],
+                0x06 => vec![data[0]],
+                // runtime settings, on channel 108.
+                0x0A if data == [0x22] => vec![0x00, 108, 14, 1, 3, 1, 2],
+                _ => vec![0x00],
+            };
+            let mut reply = vec![0x02, cmd | 0x40, payload.len() as u8];
+            reply.extend(payload);
+            reply.push(checksum(&reply));
+
+            module.replies.extend(reply);
+            module.requests.push((cmd, data));
+
+            Ok(buf.len())
+        }
+    }
+
+    impl embedded_io_async::Read for MockModule {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            poll_fn(|cx| {
+                let mut module = self.0.borrow_mut();
+                if module.replies.is_empty() {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                let len = buf.len().min(module.replies.len());
+                for (byte, reply) in buf.iter_mut().zip(module.replies.drain(..len)) {
+                    *byte = reply;
+                }
+                Poll::Ready(Ok(len))
+            })
+            .await
+        }
+    }
+
+    /// Execute the command against the mock, returning the requests it received.
+    async fn execute_mock(command: Commands) -> Vec<(u8, Vec<u8>)> {
+        let module = MockModule::default();
+        let mut storage = RadioStorage::new();
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let mut radio = radio.with_timer(TokioTimer);
+
+        tokio::select! {
+            result = execute(&mut radio, command, Vec::new()) => result.unwrap(),
+            _ = async { loop { ingress.ingest_buffered().await.ok(); } } => unreachable!(),
+        }
+
+        let requests = module.0.borrow().requests.clone();
+        requests
+    }
+
+    #[tokio::test]
+    async fn cli_send() {
+        let command = Commands::Send {
+            data: Some("hi".to_string()),
+            hex: false,
+            file: None,
+        };
+
+        assert_eq!(execute_mock(command).await, [(0x00, b"hi".to_vec())]);
+    }
+
+    #[tokio::test]
+    async fn cli_send_hex_chunked() {
+        let command = Commands::Send {
+            data: Some("00ff ".repeat(111)),
+            hex: true,
+            file: None,
+        };
+
+        let requests = execute_mock(command).await;
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1.len(), MAX_DATA_LEN);
+        assert_eq!(requests[1].1, [0x00, 0xFF]);
+    }
+
+    #[tokio::test]
+    async fn cli_echo_chunked() {
+        let module = MockModule::default();
+        let mut event = vec![0x02, 0x81, 224];
+        event.extend([0xAB; 223]);
+        event.push(0xC4);
+        event.push(checksum(&event));
+        module.0.borrow_mut().replies.extend(event);
+
+        let mut storage = RadioStorage::new();
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let mut radio = radio.with_timer(TokioTimer);
+        let command = Commands::Echo { min_rssi: None };
+
+        // echo runs until interrupted, so stop once the response has been sent.
+        tokio::select! {
+            result = execute(&mut radio, command, Vec::new()) => panic!("{:?}", result),
+            _ = async { loop { ingress.ingest_buffered().await.ok(); } } => unreachable!(),
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+
+        let requests = module.0.borrow().requests.clone();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1.len(), MAX_DATA_LEN);
+        assert_eq!(requests[1].1.len(), 224 - MAX_DATA_LEN);
+    }
+
+    #[test]
+    fn cli_parse_hex() {
+        assert_eq!(parse_hex("48 65\n6c6c"), Ok(vec![0x48, 0x65, 0x6C, 0x6C]));
+        assert!(parse_hex("486").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[tokio::test]
+    async fn cli_rssi() {
+        let command = Commands::Rssi {
+            watch: false,
+            interval: 500,
+        };
+
+        assert_eq!(execute_mock(command).await, [(0x0D, vec![])]);
+    }
+
+    #[tokio::test]
+    async fn cli_channel() {
+        let command = Commands::Channel { channel: 108 };
+
+        assert_eq!(execute_mock(command).await, [(0x06, vec![108])]);
+    }
+
+    #[tokio::test]
+    async fn cli_scan() {
+        let command = Commands::Scan {
+            from: 99,
+            to: 101,
+            dwell: 0,
+        };
+
+        assert_eq!(
+            execute_mock(command).await,
+            [
+                (0x0A, vec![0x22]),
+                (0x06, vec![100]),
+                (0x06, vec![101]),
+                (0x06, vec![108])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cli_mode() {
+        let command = Commands::Mode {
+            mode: Mode::Command,
+        };
+
+        assert_eq!(execute_mock(command).await, [(0x04, vec![0x01])]);
+    }
 }
@@ -0,0 +1,104 @@
+//! Link statistics for the `monitor` subcommand.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use wurth_telesto::{Event, Frame};
+
+/// Statistics gathered from events over one reporting interval.
+#[derive(Default)]
+pub struct Stats {
+    events: BTreeMap<Event, u32>,
+    last_rssi: Option<i8>,
+    last_send: Option<Result<(), String>>,
+}
+
+impl Stats {
+    /// Record a received event.
+    pub fn record(&mut self, event: &Frame<Event>) {
+        *self.events.entry(*event.command()).or_default() += 1;
+
+        if let Some(rssi) = event.rssi() {
+            self.last_rssi = Some(rssi);
+        }
+    }
+
+    /// Record the result of a send, described for the user if it failed.
+    pub fn record_send(&mut self, result: Result<(), String>) {
+        self.last_send = Some(result);
+    }
+
+    /// Summarise the interval as a single line, then start a new interval.
+    ///
+    /// The last RSSI and send result are kept until new values are recorded. `dropped` is the
+    /// total number of frames dropped by the ingress.
+    pub fn report(&mut self, elapsed: Duration, receive_errors: u32, dropped: usize) -> String {
+        let mut line = String::new();
+
+        match self.last_rssi {
+            Some(rssi) => write!(line, "RSSI: {}dBm", rssi).unwrap(),
+            None => line.push_str("RSSI: -"),
+        }
+
+        line.push_str(" | events/s:");
+        if self.events.is_empty() {
+            line.push_str(" none");
+        }
+        for (event, count) in &self.events {
+            let rate = *count as f32 / elapsed.as_secs_f32();
+            write!(line, " {:?} {:.1}", event, rate).unwrap();
+        }
+
+        write!(line, " | receive errors: {}", receive_errors).unwrap();
+        write!(line, " | dropped: {}", dropped).unwrap();
+
+        line.push_str(" | last send: ");
+        match &self.last_send {
+            Some(Ok(())) => line.push_str("ok"),
+            Some(Err(err)) => write!(line, "failed ({})", err).unwrap(),
+            None => line.push('-'),
+        }
+
+        self.events.clear();
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_report() {
+        let mut stats = Stats::default();
+        assert_eq!(
+            stats.report(Duration::from_secs(1), 0, 0),
+            "RSSI: - | events/s: none | receive errors: 0 | dropped: 0 | last send: -"
+        );
+
+        stats.record(&Frame::new(
+            Event::DataReceived,
+            [0x48, 0xC4].into_iter().collect(),
+        ));
+        stats.record(&Frame::new(
+            Event::DataReceived,
+            [0x48, 0xC2].into_iter().collect(),
+        ));
+        stats.record(&Frame::new(
+            Event::PacketTransmit,
+            [0x01].into_iter().collect(),
+        ));
+        stats.record_send(Err("send failed: channel busy".into()));
+        assert_eq!(
+            stats.report(Duration::from_secs(2), 3, 1),
+            "RSSI: -62dBm | events/s: DataReceived 1.0 PacketTransmit 0.5 | receive errors: 3 \
+             | dropped: 1 | last send: failed (send failed: channel busy)"
+        );
+
+        stats.record_send(Ok(()));
+        assert_eq!(
+            stats.report(Duration::from_secs(1), 0, 1),
+            "RSSI: -62dBm | events/s: none | receive errors: 0 | dropped: 1 | last send: ok"
+        );
+    }
+}
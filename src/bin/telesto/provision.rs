@@ -0,0 +1,109 @@
+//! Module provisioning from a configuration file.
+//!
+//! The configuration is a flat TOML table of `key = value` pairs, for example:
+//!
+//! ```toml
+//! channel = 108
+//! tx_power = 14
+//! source_net = 1
+//! source_address = 2
+//! destination_net = 1
+//! destination_address = 3
+//! address_mode = "network-and-address"
+//! rf_profile = "rate38k4"
+//! retries = 3
+//! ```
+
+use clap::ValueEnum;
+use wurth_telesto::{AddressMode, Channel, RfProfile, Setting};
+
+/// Parse a configuration file into the settings it describes.
+pub fn parse(text: &str) -> Result<Vec<(Setting, u8)>, String> {
+    let mut settings = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `key = value`", number + 1))?;
+        let key = key.trim();
+        let value = value.trim().trim_matches('"');
+
+        let setting = match key {
+            "channel" => (
+                Setting::DefaultRfChannel,
+                parse_u8(value).and_then(parse_channel).map(Channel::get),
+            ),
+            "tx_power" => (Setting::DefaultRfTxPower, parse_u8(value)),
+            "source_net" => (Setting::SourceNetId, parse_u8(value)),
+            "source_address" => (Setting::SourceAddr, parse_u8(value)),
+            "destination_net" => (Setting::DefaultDestinationNetId, parse_u8(value)),
+            "destination_address" => (Setting::DefaultDestinationAddr, parse_u8(value)),
+            "retries" => (Setting::RetryNumbers, parse_u8(value)),
+            "address_mode" => (
+                Setting::DefaultAddressMode,
+                AddressMode::from_str(value, true).map(|mode| mode as u8),
+            ),
+            "rf_profile" => (
+                Setting::DefaultRfProfile,
+                RfProfile::from_str(value, true).map(|profile| profile as u8),
+            ),
+            _ => return Err(format!("line {}: unknown setting `{}`", number + 1, key)),
+        };
+
+        match setting {
+            (setting, Ok(value)) => settings.push((setting, value)),
+            (_, Err(err)) => return Err(format!("line {}: {}", number + 1, err)),
+        }
+    }
+
+    Ok(settings)
+}
+
+fn parse_u8(value: &str) -> Result<u8, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{}`", value))
+}
+
+/// Validate an RF channel, as the radio's channel setter does.
+pub fn parse_channel(channel: u8) -> Result<Channel, String> {
+    Channel::new(channel).ok_or_else(|| {
+        format!(
+            "channel must be between {} and {}",
+            Channel::MIN,
+            Channel::MAX
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let settings = parse(
+            "# node 3\nchannel = 108\naddress_mode = \"network-and-address\"\nrf_profile = rate38k4\n",
+        )
+        .unwrap();
+
+        assert_eq!(settings.len(), 3);
+        assert!(matches!(settings[0], (Setting::DefaultRfChannel, 108)));
+        assert!(matches!(settings[1], (Setting::DefaultAddressMode, 0x02)));
+        assert!(matches!(settings[2], (Setting::DefaultRfProfile, 0x00)));
+
+        assert!(parse("channel = 300").is_err());
+        assert!(parse("channel = 99").is_err());
+        assert!(parse("channel = 141").is_err());
+        assert!(matches!(
+            parse("channel = 140").unwrap()[..],
+            [(Setting::DefaultRfChannel, 140)]
+        ));
+        assert!(parse("colour = 1").is_err());
+    }
+}
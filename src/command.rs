@@ -1,10 +1,21 @@
+use core::fmt;
+use core::time::Duration;
+
 /// Start byte.
 pub const START: u8 = 0x02;
 
 /// Maximum length.
 pub const MAX_PAYLOAD_LEN: usize = 224;
+/// Maximum length of user data in a single send.
+pub const MAX_DATA_LEN: usize = 220;
 const HEADER_LEN: usize = 3;
 const CHECKSUM_LEN: usize = 1;
+/// Maximum length of a complete frame, including header and checksum.
+pub const MAX_FRAME_LEN: usize = HEADER_LEN + MAX_PAYLOAD_LEN + CHECKSUM_LEN;
+
+// the length field is a single byte, and user data must fit within the payload.
+const _: () = assert!(MAX_PAYLOAD_LEN <= u8::MAX as usize);
+const _: () = assert!(MAX_DATA_LEN <= MAX_PAYLOAD_LEN);
 
 /// Command.
 #[allow(unused)]
@@ -17,11 +28,13 @@ pub enum Command {
 
 /// Request command.
 #[allow(unused)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Request {
     /// Send data to configured address.
     SendData = 0x00,
     /// Send data to specific address.
+    ///
+    /// Has no confirmation of its own, the module replies with [`Response::SendData`].
     SendDataEx = 0x01,
     /// Switch operating mode.
     SetMode = 0x04,
@@ -49,25 +62,189 @@ pub enum Request {
     FactoryReset = 0x12,
 }
 
-pub(crate) fn command(buf: &mut [u8], kind: Request, data: &[u8]) -> usize {
-    assert!(data.len() <= MAX_PAYLOAD_LEN);
+/// Time to wait for the response to most requests.
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+impl Request {
+    /// Returns true if the module needs time to settle after this request before the next one.
+    pub(crate) fn needs_guard_time(self) -> bool {
+        matches!(self, Self::SetMode | Self::Reset | Self::FactoryReset)
+    }
+
+    /// Default time to wait for the response to this request.
+    ///
+    /// Sends allow for the module's MAC retries, settings allow for the flash write.
+    pub fn default_timeout(self) -> Duration {
+        match self {
+            Self::SendData | Self::SendDataEx => Duration::from_millis(1000),
+            Self::SetUserSetting | Self::FactoryReset => Duration::from_millis(500),
+            _ => DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Response the module sends to confirm this request.
+    ///
+    /// Both sends are confirmed by [`Response::SendData`].
+    pub fn expected_response(self) -> Option<Response> {
+        match self {
+            Self::SendData | Self::SendDataEx => Some(Response::SendData),
+            Self::SetMode => Some(Response::SetMode),
+            Self::Reset => Some(Response::Reset),
+            Self::SetChannel => Some(Response::SetChannel),
+            Self::SetDestinationNetworkId => Some(Response::SetDestinationNetworkId),
+            Self::SetDestinationAddress => Some(Response::SetDestinationAddress),
+            Self::SetUserSetting => Some(Response::SetUserSetting),
+            Self::GetUserSetting => Some(Response::GetUserSetting),
+            Self::Rssi => Some(Response::Rssi),
+            Self::Shutdown => Some(Response::Shutdown),
+            Self::Standby => Some(Response::Standby),
+            Self::TransmitPower => Some(Response::TransmitPower),
+            Self::FactoryReset => Some(Response::FactoryReset),
+        }
+    }
+}
+
+/// Build a command frame, panicking if it doesn't fit.
+pub(crate) fn command(buf: &mut [u8], kind: u8, data: &[u8]) -> usize {
+    match encode_frame(buf, kind, data) {
+        Ok(len) => len,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Error building a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum FrameBuildError {
+    /// Payload is longer than [`MAX_PAYLOAD_LEN`].
+    PayloadTooLong,
+    /// Output buffer can't hold the frame.
+    BufferTooSmall,
+}
+
+impl fmt::Display for FrameBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PayloadTooLong => f.write_str("payload too long"),
+            Self::BufferTooSmall => f.write_str("buffer too small for frame"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameBuildError {}
+
+/// Error decoding a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum DecodeError {
+    /// Frame doesn't begin with the start byte.
+    StartByte,
+    /// Length field is larger than [`MAX_PAYLOAD_LEN`].
+    PayloadLength,
+    /// Frame is shorter than its length field requires.
+    Truncated,
+    /// Checksum doesn't match the frame contents.
+    Checksum,
+    /// Command id is not recognised, carrying the raw command byte.
+    UnknownCommand(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartByte => f.write_str("missing start byte"),
+            Self::PayloadLength => f.write_str("payload length too long"),
+            Self::Truncated => f.write_str("frame truncated"),
+            Self::Checksum => f.write_str("checksum mismatch"),
+            Self::UnknownCommand(cmd) => write!(f, "unknown command {:#04x}", cmd),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Build a frame with the given command id and payload into `buf`.
+///
+/// Adds the start byte, length and checksum, returning the length of the frame.
+pub fn encode_frame(buf: &mut [u8], command: u8, data: &[u8]) -> Result<usize, FrameBuildError> {
+    if data.len() > MAX_PAYLOAD_LEN {
+        return Err(FrameBuildError::PayloadTooLong);
+    }
 
     let len = HEADER_LEN + data.len() + CHECKSUM_LEN;
-    assert!(buf.len() >= len);
+    if buf.len() < len {
+        return Err(FrameBuildError::BufferTooSmall);
+    }
 
     buf[0] = START;
-    buf[1] = kind as u8;
+    buf[1] = command;
     buf[2] = data.len() as u8;
     buf[3..(data.len() + 3)].copy_from_slice(data);
     buf[len - 1] = checksum(&buf[0..len - 1]);
 
-    len
+    Ok(len)
 }
 
-pub(crate) fn checksum(bytes: &[u8]) -> u8 {
+/// Decode a complete frame, returning its command id and payload.
+///
+/// Bytes following the frame are ignored.
+pub fn decode_frame(raw: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    let [start, command, len, ..] = *raw else {
+        return Err(if raw.first().is_some_and(|&b| b != START) {
+            DecodeError::StartByte
+        } else {
+            DecodeError::Truncated
+        });
+    };
+
+    if start != START {
+        return Err(DecodeError::StartByte);
+    }
+
+    let len = len as usize;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(DecodeError::PayloadLength);
+    }
+
+    let end = HEADER_LEN + len;
+    let Some(&expected) = raw.get(end) else {
+        return Err(DecodeError::Truncated);
+    };
+
+    if checksum(&raw[..end]) != expected {
+        return Err(DecodeError::Checksum);
+    }
+
+    Ok((command, &raw[HEADER_LEN..end]))
+}
+
+/// Frame checksum, the XOR of every byte preceding the checksum.
+pub fn checksum(bytes: &[u8]) -> u8 {
     bytes.iter().fold(0, |acc, &x| acc ^ x)
 }
 
+/// Incremental frame checksum, for frames which are never held in a single buffer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChecksumState(u8);
+
+impl ChecksumState {
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Add the bytes to the checksum.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0 ^= checksum(bytes);
+    }
+
+    /// Checksum of all bytes added so far.
+    pub const fn finish(self) -> u8 {
+        self.0
+    }
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<u8> for Request {
     fn into(self) -> u8 {
@@ -76,15 +253,25 @@ impl Into<u8> for Request {
 }
 
 /// Send data error kind.
+///
+/// Both [`Request::SendData`] and [`Request::SendDataEx`] are confirmed by a
+/// [`Response::SendData`] frame carrying the same status codes, so this applies to either.
 #[derive(Debug, Clone, Copy)]
 pub enum SendDataError {
     /// No ACK received within a time-out after using all MAC retrys.
+    ///
+    /// Not returned by [`Radio::send`](crate::Radio::send) with acknowledgements disabled, see
+    /// [`Radio::acks_enabled`](crate::Radio::acks_enabled).
     AckTimeout,
     /// Invalid channel selected.
     InvalidChannel,
     /// Channel is busy.
     ChannelBusy,
     /// Module is currently busy.
+    ///
+    /// This is the module's only back-pressure signal, it has no separate buffer full
+    /// indication. As each send waits for its confirmation, a caller pacing on these results
+    /// never has more than one packet queued in the module.
     ModuleBusy,
     /// Payload too long.
     PayloadInvalid,
@@ -92,6 +279,49 @@ pub enum SendDataError {
     Unknown(u8),
 }
 
+impl SendDataError {
+    /// Returns true if the same send may succeed when retried.
+    ///
+    /// Timeouts and busy conditions are transient, an invalid channel or payload will fail
+    /// again, as will unrecognised errors.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::AckTimeout | Self::ChannelBusy | Self::ModuleBusy
+        )
+    }
+}
+
+impl fmt::Display for SendDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AckTimeout => f.write_str("no acknowledgement received"),
+            Self::InvalidChannel => f.write_str("invalid channel"),
+            Self::ChannelBusy => f.write_str("channel busy"),
+            Self::ModuleBusy => f.write_str("module busy"),
+            Self::PayloadInvalid => f.write_str("invalid payload"),
+            Self::Unknown(code) => write!(f, "unknown error {:#04x}", code),
+        }
+    }
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for SendDataError {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Self::AckTimeout => defmt::write!(f, "no acknowledgement received"),
+            Self::InvalidChannel => defmt::write!(f, "invalid channel"),
+            Self::ChannelBusy => defmt::write!(f, "channel busy"),
+            Self::ModuleBusy => defmt::write!(f, "module busy"),
+            Self::PayloadInvalid => defmt::write!(f, "invalid payload"),
+            Self::Unknown(code) => defmt::write!(f, "unknown error {=u8:#04x}", code),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SendDataError {}
+
 impl From<u8> for SendDataError {
     fn from(value: u8) -> Self {
         match value {
@@ -106,7 +336,7 @@ impl From<u8> for SendDataError {
 }
 
 /// Command response.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Response {
     /// Data has been sent.
     SendData = 0x40,
@@ -159,13 +389,16 @@ impl Response {
 }
 
 /// Event kind.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Event {
     /// Data has been repeated.
     DataRepeat,
     /// Data has been received.
     DataReceived,
     /// Reset has been applied.
+    ///
+    /// Sent after every restart, the payload doesn't identify the cause. A reset following
+    /// [`Request::Reset`] or [`Request::FactoryReset`] was commanded, any other was not.
     Reset,
     /// Woke up from standby mode.
     Wakeup,
@@ -174,13 +407,43 @@ pub enum Event {
 }
 
 /// Operating mode.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum Mode {
     /// Transparent mode.
-    Transparent,
+    Transparent = 0x00,
     /// Command mode.
-    Command,
+    Command = 0x01,
+}
+
+impl Mode {
+    /// Byte sent in the set mode request.
+    ///
+    /// Every variant must map to a mode the module accepts, so this is exhaustive rather than
+    /// a cast.
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            Self::Transparent => 0x00,
+            Self::Command => 0x01,
+        }
+    }
+
+    pub fn try_from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0x00 => Some(Self::Transparent),
+            0x01 => Some(Self::Command),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u8> for Mode {
+    /// The unrecognised mode byte.
+    type Error = u8;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        Self::try_from_raw(raw).ok_or(raw)
+    }
 }
 
 impl Event {
@@ -210,4 +473,170 @@ mod tests {
 
         assert_eq!(checksum(&data), 0x0F);
     }
+
+    #[test]
+    fn frame_empty_payload() {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let len = command(&mut buf, Request::Reset as u8, &[]);
+
+        assert_eq!(&buf[..len], &[START, 0x05, 0x00, 0x07]);
+    }
+
+    #[test]
+    fn frame_single_byte_payload() {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let len = command(&mut buf, Request::SetChannel as u8, &[0x6C]);
+
+        assert_eq!(&buf[..len], &[START, 0x06, 0x01, 0x6C, 0x69]);
+    }
+
+    #[test]
+    fn frame_max_payload() {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let len = command(&mut buf, Request::SendData as u8, &[0xAA; MAX_PAYLOAD_LEN]);
+
+        assert_eq!(len, MAX_FRAME_LEN);
+        assert_eq!(&buf[..3], &[START, 0x00, MAX_PAYLOAD_LEN as u8]);
+        assert!(buf[3..len - 1].iter().all(|&b| b == 0xAA));
+        assert_eq!(buf[len - 1], checksum(&buf[..len - 1]));
+    }
+
+    #[test]
+    fn frame_build_errors() {
+        let mut buf = [0; 4];
+
+        assert_eq!(
+            encode_frame(&mut buf, 0x06, &[0x6C]),
+            Err(FrameBuildError::BufferTooSmall)
+        );
+        assert_eq!(
+            encode_frame(&mut [0; MAX_FRAME_LEN + 1], 0x00, &[0; MAX_PAYLOAD_LEN + 1]),
+            Err(FrameBuildError::PayloadTooLong)
+        );
+    }
+
+    #[test]
+    fn frame_decode() {
+        let frame = [START, 0x46, 0x01, 0x6C, 0x29];
+
+        assert_eq!(decode_frame(&frame), Ok((0x46, &[0x6C][..])));
+        assert_eq!(decode_frame(&frame[..4]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decode_frame(&[START, 0x46, 0x01, 0x6C, 0x00]),
+            Err(DecodeError::Checksum)
+        );
+        assert_eq!(decode_frame(&[0x00, 0x46]), Err(DecodeError::StartByte));
+    }
+
+    #[test]
+    #[should_panic]
+    fn frame_oversized_payload() {
+        let mut buf = [0; MAX_FRAME_LEN + 1];
+        command(&mut buf, Request::SendData as u8, &[0; MAX_PAYLOAD_LEN + 1]);
+    }
+
+    #[test]
+    fn mode_to_raw() {
+        assert_eq!(Mode::Transparent.to_raw(), 0x00);
+        assert_eq!(Mode::Command.to_raw(), 0x01);
+    }
+
+    #[test]
+    fn mode_from_raw() {
+        for mode in [Mode::Transparent, Mode::Command] {
+            assert_eq!(Mode::try_from(mode.to_raw()), Ok(mode));
+        }
+        assert_eq!(Mode::try_from(0x02), Err(0x02));
+    }
+
+    #[test]
+    fn frame_checksum_incremental() {
+        let mut state = ChecksumState::new();
+        state.update(&[0x02, 0x00, 0x0C]);
+        state.update(&[0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x20]);
+        state.update(&[0x57, 0x6F, 0x72, 0x6C, 0x64, 0x21]);
+
+        assert_eq!(state.finish(), 0x0F);
+    }
+
+    #[test]
+    fn send_data_error_codes() {
+        assert!(matches!(
+            SendDataError::from(0x01),
+            SendDataError::AckTimeout
+        ));
+        assert!(matches!(
+            SendDataError::from(0x02),
+            SendDataError::InvalidChannel
+        ));
+        assert!(matches!(
+            SendDataError::from(0x03),
+            SendDataError::ChannelBusy
+        ));
+        assert!(matches!(
+            SendDataError::from(0x04),
+            SendDataError::ModuleBusy
+        ));
+        assert!(matches!(
+            SendDataError::from(0xFF),
+            SendDataError::PayloadInvalid
+        ));
+        assert!(matches!(
+            SendDataError::from(0x05),
+            SendDataError::Unknown(0x05)
+        ));
+    }
+
+    #[test]
+    fn request_expected_response() {
+        assert_eq!(
+            Request::SendDataEx.expected_response(),
+            Some(Response::SendData)
+        );
+
+        // confirmations echo the request id with bit 6 set.
+        for request in [Request::SetChannel, Request::Rssi, Request::FactoryReset] {
+            let response = request.expected_response().unwrap();
+            assert_eq!(response as u8, request as u8 | 0x40);
+        }
+    }
+
+    #[test]
+    fn send_data_error_unknown_code() {
+        let err = SendDataError::from(0x07);
+
+        assert!(matches!(err, SendDataError::Unknown(0x07)));
+        assert_eq!(err.to_string(), "unknown error 0x07");
+    }
+}
+
+#[cfg(all(test, feature = "proptest"))]
+mod proptests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn frame_round_trip(command: u8, data in vec(any::<u8>(), 0..=MAX_PAYLOAD_LEN)) {
+            let mut buf = [0; MAX_FRAME_LEN];
+
+            let size = encode_frame(&mut buf, command, &data).unwrap();
+            prop_assert_eq!(size, HEADER_LEN + data.len() + CHECKSUM_LEN);
+            prop_assert_eq!(buf[size - 1], checksum(&buf[..size - 1]));
+            prop_assert_eq!(decode_frame(&buf[..size]), Ok((command, &data[..])));
+        }
+
+        #[test]
+        fn frame_decode_garbage(start: bool, mut raw in vec(any::<u8>(), 0..MAX_FRAME_LEN + 8)) {
+            if start && !raw.is_empty() {
+                raw[0] = START;
+            }
+
+            // only checks that nothing panics, and that a decoded payload lies within the input.
+            if let Ok((_, payload)) = decode_frame(&raw) {
+                prop_assert!(HEADER_LEN + payload.len() < raw.len());
+            }
+        }
+    }
 }
@@ -0,0 +1,109 @@
+//! Transmit duty cycle accounting.
+
+use core::time::Duration;
+
+use crate::RfProfile;
+
+/// Monotonic time source for [`DutyCycle`] accounting.
+///
+/// Implemented by the radio's [`Timer`](crate::Timer), see
+/// [`Radio::with_duty_cycle`](crate::Radio::with_duty_cycle).
+pub trait Clock {
+    /// Time elapsed since an arbitrary fixed point, which must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+/// Limits the air time used within each window.
+///
+/// Enable for a radio with [`Radio::with_duty_cycle`](crate::Radio::with_duty_cycle).
+///
+/// Air time is estimated with [`RfProfile::air_time`], which is a lower bound, so the budget
+/// should leave a margin below the regulatory limit. Windows are fixed rather than sliding: the
+/// budget is restored in full once `window` has elapsed since the first send of a window.
+#[derive(Debug, Clone, Copy)]
+pub struct DutyCycle {
+    profile: RfProfile,
+    budget: Duration,
+    window: Duration,
+    start: Option<Duration>,
+    used: Duration,
+}
+
+impl DutyCycle {
+    /// Allow at most `budget` of air time in each `window`, for packets sent with `profile`.
+    ///
+    /// For example a 1% duty cycle is 36 seconds in a one hour window.
+    pub const fn new(profile: RfProfile, budget: Duration, window: Duration) -> Self {
+        Self {
+            profile,
+            budget,
+            window,
+            start: None,
+            used: Duration::ZERO,
+        }
+    }
+
+    /// Account for sending `len` bytes at `now`.
+    ///
+    /// Returns false, without using any of the budget, if the send would exceed it.
+    pub fn reserve(&mut self, now: Duration, len: usize) -> bool {
+        if !self.fits(now, len) {
+            return false;
+        }
+
+        if self.window_elapsed(now) {
+            self.start = Some(now);
+            self.used = Duration::ZERO;
+        }
+        self.used += self.profile.air_time(len);
+        true
+    }
+
+    /// Returns true if sending `len` bytes at `now` stays within the budget, without using any
+    /// of it.
+    pub fn fits(&self, now: Duration, len: usize) -> bool {
+        let used = if self.window_elapsed(now) {
+            Duration::ZERO
+        } else {
+            self.used
+        };
+
+        used + self.profile.air_time(len) <= self.budget
+    }
+
+    fn window_elapsed(&self, now: Duration) -> bool {
+        self.start
+            .is_none_or(|start| now.saturating_sub(start) >= self.window)
+    }
+
+    /// Air time used in the current window.
+    pub fn used(&self) -> Duration {
+        self.used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duty_cycle_window() {
+        // 3 bytes take 20ms at 1.2 kbit/s.
+        let mut duty = DutyCycle::new(
+            RfProfile::Rate1k2,
+            Duration::from_millis(40),
+            Duration::from_secs(1),
+        );
+
+        assert!(duty.fits(Duration::ZERO, 3));
+        assert_eq!(duty.used(), Duration::ZERO);
+        assert!(duty.reserve(Duration::ZERO, 3));
+        assert!(duty.reserve(Duration::from_millis(500), 3));
+        assert!(!duty.fits(Duration::from_millis(600), 3));
+        assert!(!duty.reserve(Duration::from_millis(600), 3));
+        assert_eq!(duty.used(), Duration::from_millis(40));
+
+        assert!(duty.reserve(Duration::from_secs(1), 3));
+        assert_eq!(duty.used(), Duration::from_millis(20));
+    }
+}
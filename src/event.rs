@@ -0,0 +1,227 @@
+//! Event payload parsers.
+
+use crate::{Address, AddressMode, Event, Frame};
+
+/// Report carried by a [`Event::PacketTransmit`] event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TransmitReport {
+    /// Transmit result status, zero on success.
+    ///
+    /// Not included by all firmware versions.
+    pub status: Option<u8>,
+}
+
+impl TransmitReport {
+    /// Parse the packet transmit event payload.
+    ///
+    /// An empty payload gives an empty report.
+    pub fn from_raw(raw: &[u8]) -> Self {
+        Self {
+            status: raw.first().copied(),
+        }
+    }
+}
+
+/// Data received from another node, as carried by a [`Event::DataReceived`] event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceivedFrame<'a> {
+    /// Source network id, in modes which include it.
+    pub source_net: Option<u8>,
+    /// Source address, in modes which include it.
+    pub source_address: Option<Address>,
+    /// User data.
+    pub data: &'a [u8],
+    /// Receive signal strength in dBm.
+    pub rssi: i8,
+}
+
+impl<'a> ReceivedFrame<'a> {
+    /// Parse a received data payload for the sender's address mode.
+    ///
+    /// Layout is the source network id (if the mode includes one), the source address (one or
+    /// two bytes, little endian), the data and a trailing RSSI byte. Returns [`None`] if the
+    /// payload is too short for the mode.
+    pub fn from_raw(mode: AddressMode, raw: &'a [u8]) -> Option<Self> {
+        let (&rssi, raw) = raw.split_last()?;
+        Self::from_parts(mode, raw, rssi as i8)
+    }
+
+    /// Parse a received data payload whose RSSI byte has already been split off.
+    fn from_parts(mode: AddressMode, raw: &'a [u8], rssi: i8) -> Option<Self> {
+        let (source_net, raw) = match mode {
+            AddressMode::NetworkAndAddress | AddressMode::NetworkAndLongAddress => {
+                let (&net, raw) = raw.split_first()?;
+                (Some(net), raw)
+            }
+            AddressMode::None | AddressMode::Address => (None, raw),
+        };
+
+        let source_address = match mode {
+            AddressMode::None => None,
+            _ => Some(Address::from_raw(mode, raw)?),
+        };
+
+        Some(Self {
+            source_net,
+            source_address,
+            data: &raw[mode.address_len()..],
+            rssi,
+        })
+    }
+}
+
+impl Frame<Event> {
+    /// Received data, if this is a data received event.
+    ///
+    /// `mode` is the address mode of the sending node, see [`ReceivedFrame::from_raw`].
+    pub fn received(&self, mode: AddressMode) -> Option<ReceivedFrame<'_>> {
+        match self.command() {
+            Event::DataReceived => match self.rssi {
+                Some(rssi) => ReceivedFrame::from_parts(mode, self.data(), rssi),
+                None => ReceivedFrame::from_raw(mode, self.data()),
+            },
+            _ => None,
+        }
+    }
+
+    /// Receive signal strength in dBm, if this is a received data event.
+    ///
+    /// Returns [`None`] for other events, or if the payload is empty.
+    pub fn rssi(&self) -> Option<i8> {
+        if self.rssi.is_some() {
+            return self.rssi;
+        }
+
+        match self.command() {
+            Event::DataReceived | Event::DataRepeat => self.data().last().map(|&rssi| rssi as i8),
+            _ => None,
+        }
+    }
+
+    /// Transmit report, if this is a packet transmit event.
+    pub fn transmit_report(&self) -> Option<TransmitReport> {
+        match self.command() {
+            Event::PacketTransmit => Some(TransmitReport::from_raw(self.data())),
+            _ => None,
+        }
+    }
+}
+
+/// Detects gaps in an application sequence number carried in received data.
+///
+/// The module doesn't number the packets it receives, so applications wanting to measure loss
+/// embed a wrapping `u8` counter in their own payload and feed each one received to
+/// [`SequenceTracker::observe`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SequenceTracker {
+    expected: Option<u8>,
+    lost: u32,
+}
+
+impl SequenceTracker {
+    pub const fn new() -> Self {
+        Self {
+            expected: None,
+            lost: 0,
+        }
+    }
+
+    /// Record a received sequence number, returning how many were skipped since the last one.
+    ///
+    /// A repeated or stale number (received after a later one) counts as no loss.
+    pub fn observe(&mut self, sequence: u8) -> u8 {
+        let gap = match self.expected {
+            Some(expected) => sequence.wrapping_sub(expected),
+            None => 0,
+        };
+
+        // treat a jump of more than half the range as a late or duplicate packet.
+        if gap >= 0x80 {
+            return 0;
+        }
+
+        self.expected = Some(sequence.wrapping_add(1));
+        self.lost += u32::from(gap);
+
+        gap
+    }
+
+    /// Sequence number expected next, if any have been observed.
+    pub fn expected_next(&self) -> Option<u8> {
+        self.expected
+    }
+
+    /// Total sequence numbers skipped so far.
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{payload, Payload};
+
+    #[test]
+    fn transmit_report_parse() {
+        assert_eq!(TransmitReport::from_raw(&[]).status, None);
+        assert_eq!(TransmitReport::from_raw(&[0x00]).status, Some(0x00));
+    }
+
+    #[test]
+    fn rssi() {
+        let data = [0x48, 0xC4];
+
+        assert_eq!(
+            Frame::new(Event::DataReceived, payload(&data)).rssi(),
+            Some(-60)
+        );
+        assert_eq!(
+            Frame::new(Event::PacketTransmit, payload(&data)).rssi(),
+            None
+        );
+        assert_eq!(Frame::new(Event::DataRepeat, Payload::new()).rssi(), None);
+    }
+
+    #[test]
+    fn sequence_gaps() {
+        let mut tracker = SequenceTracker::new();
+
+        assert_eq!(tracker.observe(0xFE), 0);
+        assert_eq!(tracker.observe(0xFF), 0);
+        assert_eq!(tracker.observe(0x02), 2);
+        assert_eq!(tracker.observe(0x01), 0);
+        assert_eq!(tracker.expected_next(), Some(0x03));
+        assert_eq!(tracker.lost(), 2);
+    }
+
+    #[test]
+    fn received_frame_layouts() {
+        let frame = ReceivedFrame::from_raw(AddressMode::None, &[0x48, 0x69, 0xC4]).unwrap();
+        assert_eq!((frame.source_net, frame.source_address), (None, None));
+        assert_eq!((frame.data, frame.rssi), (&[0x48, 0x69][..], -60));
+
+        let frame = ReceivedFrame::from_raw(AddressMode::Address, &[0x02, 0x48, 0xC4]).unwrap();
+        assert_eq!(frame.source_address, Some(Address::Short(0x02)));
+        assert_eq!(frame.data, &[0x48]);
+
+        let frame =
+            ReceivedFrame::from_raw(AddressMode::NetworkAndAddress, &[0x01, 0x02, 0x48, 0xC4])
+                .unwrap();
+        assert_eq!(frame.source_net, Some(0x01));
+        assert_eq!(frame.source_address, Some(Address::Short(0x02)));
+        assert_eq!(frame.data, &[0x48]);
+
+        let raw = [0x01, 0x34, 0x12, 0x48, 0xC4];
+        let frame = ReceivedFrame::from_raw(AddressMode::NetworkAndLongAddress, &raw).unwrap();
+        assert_eq!(frame.source_net, Some(0x01));
+        assert_eq!(frame.source_address, Some(Address::Long(0x1234)));
+        assert_eq!(frame.data, &[0x48]);
+
+        assert_eq!(
+            ReceivedFrame::from_raw(AddressMode::NetworkAndAddress, &[0x01, 0xC4]),
+            None
+        );
+        assert_eq!(ReceivedFrame::from_raw(AddressMode::None, &[]), None);
+    }
+}
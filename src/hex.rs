@@ -0,0 +1,31 @@
+use core::fmt;
+
+/// Hex formatting wrapper for payloads.
+///
+/// Displays bytes as space separated, two digit lowercase hex, e.g. `48 65 6c 6c 6f`.
+#[derive(Debug, Clone, Copy)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_format() {
+        assert_eq!(Hex(&[0x48, 0x00, 0xFF]).to_string(), "48 00 ff");
+        assert_eq!(Hex(&[]).to_string(), "");
+    }
+}
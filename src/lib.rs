@@ -1,347 +1,4175 @@
 #![cfg_attr(all(not(test), not(feature = "std")), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+/// Trace a frame when the `log` feature is enabled.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::trace!($($arg)*);
+    };
+}
+
+mod assembler;
 mod command;
+mod duty;
+mod event;
+mod hex;
+mod pin;
+mod setting;
+mod storage;
+#[cfg(feature = "futures")]
+mod stream;
+mod tap;
+mod timer;
 #[cfg(feature = "tokio")]
 pub mod tokio;
+mod transceiver;
 
-pub use command::{Event, Mode, Response};
+pub use command::{
+    checksum, decode_frame, encode_frame, ChecksumState, DecodeError, Event, FrameBuildError, Mode,
+    Request, Response, SendDataError, MAX_DATA_LEN, MAX_FRAME_LEN, MAX_PAYLOAD_LEN,
+};
+pub use duty::{Clock, DutyCycle};
+pub use event::{ReceivedFrame, SequenceTracker, TransmitReport};
+pub use hex::Hex;
+pub use pin::ResetPin;
+pub use setting::{
+    Address, AddressMode, Channel, ConfigFlags, FirmwareVersion, RepeaterFlags, RfProfile,
+    RuntimeSettings, Setting,
+};
+pub use storage::{OverflowPolicy, RadioStorage};
+#[cfg(feature = "futures")]
+pub use stream::EventStream;
+pub use tap::Tap;
+pub use timer::{MaybeTimer, NoTimer, Timer};
+pub use transceiver::Transceiver;
 
-use command::{command, Request, SendDataError, MAX_PAYLOAD_LEN, START};
+use assembler::FrameAssembler;
+use command::{command, DEFAULT_TIMEOUT, START};
+use core::fmt;
 use core::future::poll_fn;
+use core::ops::RangeInclusive;
 use core::task::Poll;
-use embedded_io_async::{Read, Write};
+use core::time::Duration;
+use embedded_io_async::{Error as _, ErrorKind, Read, ReadExactError, Write};
 use heapless::spsc::{Consumer, Producer, Queue};
-use heapless::Vec;
+use heapless::{LinearMap, Vec};
+use setting::{le_u16, le_u32};
+use storage::{EventConsumer, EventProducer, IngressGuard, SharedState};
+use timer::{with_timeout, Timeout};
+
+/// Number of valid channels, see [`Radio::channel_scan`].
+pub const CHANNEL_COUNT: usize = (Channel::MAX - Channel::MIN) as usize + 1;
+
+/// Time [`Radio::ready`] waits for the module to respond.
+pub const READY_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// Frame payload.
+pub type Payload = Vec<u8, MAX_PAYLOAD_LEN>;
+
+/// Frame payload storage.
+///
+/// Every frame reserves [`MAX_PAYLOAD_LEN`] bytes inline, unless the `alloc` feature is enabled
+/// in which case payloads are heap allocated to their actual length.
+#[cfg(not(feature = "alloc"))]
+pub(crate) type Buffer = Payload;
+#[cfg(feature = "alloc")]
+pub(crate) type Buffer = alloc::vec::Vec<u8>;
+
+/// Copy data into a payload.
+///
+/// Panics if the data is longer than [`MAX_PAYLOAD_LEN`].
+#[cfg(test)]
+pub(crate) fn payload(data: &[u8]) -> Payload {
+    Vec::from_slice(data).unwrap()
+}
+
+/// Copy received data into payload storage.
+///
+/// Panics if the data is longer than [`MAX_PAYLOAD_LEN`].
+pub(crate) fn buffer(data: &[u8]) -> Buffer {
+    assert!(data.len() <= MAX_PAYLOAD_LEN);
+
+    #[cfg(not(feature = "alloc"))]
+    return Vec::from_slice(data).unwrap();
+    #[cfg(feature = "alloc")]
+    return data.into();
+}
 
 /// Command/response frame.
 #[derive(Debug)]
 pub struct Frame<T> {
     command: T,
-    data: Vec<u8, MAX_PAYLOAD_LEN>,
+    data: Buffer,
+    rssi: Option<i8>,
 }
 
 impl<T> Frame<T> {
+    pub fn new(command: T, data: Payload) -> Self {
+        #[cfg(not(feature = "alloc"))]
+        return Self::from_buffer(command, data);
+        #[cfg(feature = "alloc")]
+        return Self::from_buffer(command, buffer(&data));
+    }
+
+    /// Create a frame from a heap allocated payload.
+    ///
+    /// Panics if the data is longer than [`MAX_PAYLOAD_LEN`].
+    #[cfg(feature = "alloc")]
+    pub fn from_vec(command: T, data: alloc::vec::Vec<u8>) -> Self {
+        assert!(data.len() <= MAX_PAYLOAD_LEN);
+        Self::from_buffer(command, data)
+    }
+
+    fn from_buffer(command: T, data: Buffer) -> Self {
+        Self {
+            command,
+            data,
+            rssi: None,
+        }
+    }
+
+    /// Split the frame into its command, payload and RSSI.
+    ///
+    /// The RSSI is only held separately once split off by an [`Ingress`] configured with
+    /// [`Ingress::with_rssi_split`], otherwise it's [`None`] and stays in the payload.
+    ///
+    /// With the `alloc` feature this copies the payload, use [`Frame::into_vec_parts`] to avoid
+    /// that.
+    pub fn into_parts(self) -> (T, Payload, Option<i8>) {
+        #[cfg(not(feature = "alloc"))]
+        return (self.command, self.data, self.rssi);
+        // A frame never holds more than `MAX_PAYLOAD_LEN` bytes.
+        #[cfg(feature = "alloc")]
+        return (
+            self.command,
+            Vec::from_slice(&self.data).unwrap(),
+            self.rssi,
+        );
+    }
+
+    /// Split the frame into its command, heap allocated payload and RSSI without copying.
+    ///
+    /// See [`Frame::into_parts`].
+    #[cfg(feature = "alloc")]
+    pub fn into_vec_parts(self) -> (T, alloc::vec::Vec<u8>, Option<i8>) {
+        (self.command, self.data, self.rssi)
+    }
+
     pub fn command(&self) -> &T {
         &self.command
     }
 
+    /// Frame payload.
+    ///
+    /// For received data events this ends with the RSSI byte, unless it was split off by an
+    /// [`Ingress`] configured with [`Ingress::with_rssi_split`].
     pub fn data(&self) -> &[u8] {
         &self.data
     }
 }
 
-/// Error kind.
+impl Frame<Response> {
+    /// Decode a raw response frame, see [`decode_frame`].
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let (cmd, data) = decode_frame(raw)?;
+        let command = Response::try_from_raw(cmd).ok_or(DecodeError::UnknownCommand(cmd))?;
+        Ok(Self::from_buffer(command, buffer(data)))
+    }
+}
+
+impl Frame<Event> {
+    /// Decode a raw event frame, see [`decode_frame`].
+    pub fn decode(raw: &[u8]) -> Result<Self, DecodeError> {
+        let (cmd, data) = decode_frame(raw)?;
+        let command = Event::try_from_raw(cmd).ok_or(DecodeError::UnknownCommand(cmd))?;
+        Ok(Self::from_buffer(command, buffer(data)))
+    }
+}
+
+/// Command error kind.
 #[derive(Debug)]
-pub enum Error<S, IO> {
-    Status(S),
+pub enum CommandError<IO> {
+    /// Data send was rejected by the module.
+    Send(SendDataError),
+    /// Module responded with an error status, carrying the raw status byte.
+    Status(u8),
+    /// Response didn't match the request, or its payload was missing or couldn't be parsed.
+    UnexpectedResponse,
     Io(IO),
+    /// Module is in standby or shutdown and won't respond to commands.
+    NotAwake,
+    /// No response was received within the timeout.
+    Timeout,
+    /// A previous command is still awaiting its response, see [`Radio::clear_transaction`].
+    Busy,
+    /// The [`Ingress`] has been dropped, so no response can be received.
+    ///
+    /// Only detected for a radio created with [`Radio::from_storage`], or by a [`Transceiver`]
+    /// whose receive path has stopped, see [`Transceiver::ingest_error`].
+    IngressGone,
+    /// Setting is read only, or the value doesn't match its [`Setting::value_len`].
+    InvalidSetting,
+    /// Send would exceed the air time budget set with [`Radio::with_duty_cycle`].
+    DutyCycleExceeded,
+    /// Destination address doesn't fit the address mode, see [`Radio::send_to`].
+    InvalidAddress,
+}
+
+/// Step of [`Radio::configure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigureStep {
+    Channel,
+    DestinationNet,
+    DestinationAddress,
+}
+
+/// Error from [`Radio::configure`], recording the step which failed.
+#[derive(Debug)]
+pub struct ConfigureError<IO> {
+    pub step: ConfigureStep,
+    pub error: CommandError<IO>,
+}
+
+impl<IO> fmt::Display for CommandError<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Send(err) => write!(f, "send failed: {}", err),
+            Self::Status(status) => write!(f, "module reported an error {:#04x}", status),
+            Self::UnexpectedResponse => f.write_str("unexpected response from module"),
+            Self::Io(_) => f.write_str("serial port error"),
+            Self::NotAwake => f.write_str("module is not awake"),
+            Self::Timeout => f.write_str("timed out waiting for response"),
+            Self::Busy => f.write_str("a previous command is still in progress"),
+            Self::IngressGone => f.write_str("receive path has stopped"),
+            Self::InvalidSetting => {
+                f.write_str("setting is read only or the value has the wrong length")
+            }
+            Self::DutyCycleExceeded => f.write_str("duty cycle budget exhausted"),
+            Self::InvalidAddress => f.write_str("address doesn't match the address mode"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: std::error::Error + 'static> std::error::Error for CommandError<IO> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Send(err) => Some(err),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigureStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Channel => f.write_str("channel"),
+            Self::DestinationNet => f.write_str("destination network id"),
+            Self::DestinationAddress => f.write_str("destination address"),
+        }
+    }
+}
+
+impl<IO> fmt::Display for ConfigureError<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "setting {} failed: {}", self.step, self.error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: std::error::Error + 'static> std::error::Error for ConfigureError<IO> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
 }
 
+/// Reads the time from a timer which is also a [`Clock`], see [`Radio::with_duty_cycle`].
+type ClockNow<T> = fn(&T) -> Duration;
+
 /// Radio module instance.
-pub struct Radio<'a, W>
+///
+/// Commands are issued by a single writer: each one writes a request and then waits for its
+/// response, and only one command may be in progress at a time. If a command future is dropped
+/// before its response arrives, further commands return [`CommandError::Busy`] until
+/// [`Radio::clear_transaction`] is called.
+pub struct Radio<'a, W, T = NoTimer, const EVENTS: usize = 16>
 where
     W: Write,
 {
     serial: W,
     response: Consumer<'a, Frame<Response>, 2>,
-    event: Consumer<'a, Frame<Event>, 16>,
+    event: EventConsumer<'a, EVENTS>,
+    timer: T,
+    guard_time: Option<Duration>,
+    guard_pending: bool,
+    awake: bool,
+    timeouts: LinearMap<Request, Duration, 16>,
+    default_timeout: Option<Duration>,
+    busy_retries: u8,
+    busy_delay: Duration,
+    write_timeout: Option<Duration>,
+    in_flight: bool,
+    discard_stale: bool,
+    orphaned: bool,
+    acks: bool,
+    duty_cycle: Option<(DutyCycle, ClockNow<T>)>,
+    state: Option<&'a SharedState>,
 }
 
 impl<'a, W> Radio<'a, W>
 where
     W: Write,
 {
+    /// Create a radio using bare queues.
+    ///
+    /// The queues carry nothing else, so a dropped [`Ingress`] and mode changes aren't seen by
+    /// the other half. Use [`Radio::from_storage`] for those.
     pub fn new<R: Read>(
         writer: W,
         reader: R,
         response_queue: &'a mut Queue<Frame<Response>, 2>,
         event_queue: &'a mut Queue<Frame<Event>, 16>,
     ) -> (Self, Ingress<'a, R>) {
-        let (response_producer, response_consumer) = response_queue.split();
         let (event_producer, event_consumer) = event_queue.split();
+        Self::build(
+            writer,
+            reader,
+            response_queue,
+            EventConsumer::Split(event_consumer),
+            EventProducer::Split(event_producer),
+            None,
+        )
+    }
+}
+
+impl<'a, W, const EVENTS: usize> Radio<'a, W, NoTimer, EVENTS>
+where
+    W: Write,
+{
+    /// Create a radio using the queues held by `storage`.
+    ///
+    /// Commands return [`CommandError::IngressGone`] rather than waiting forever once the
+    /// returned [`Ingress`] has been dropped, for example if its task panicked. Events received
+    /// while the event queue is full are dropped following the storage's
+    /// [`OverflowPolicy`].
+    pub fn from_storage<R: Read>(
+        writer: W,
+        reader: R,
+        storage: &'a mut RadioStorage<EVENTS>,
+    ) -> (Self, Ingress<'a, R, NoTimer, EVENTS>) {
+        let RadioStorage {
+            response,
+            event,
+            state,
+            policy,
+        } = storage;
+        let state: &'a SharedState = state;
+        state.reset();
+
+        let (event_consumer, event_producer) = match policy {
+            OverflowPolicy::DropNewest => {
+                let (producer, consumer) = event.get_mut().split();
+                (
+                    EventConsumer::Split(consumer),
+                    EventProducer::Split(producer),
+                )
+            }
+            OverflowPolicy::DropOldest => {
+                let event = &*event;
+                (
+                    EventConsumer::Shared(event, state),
+                    EventProducer::Shared(event, state),
+                )
+            }
+        };
+
+        Self::build(
+            writer,
+            reader,
+            response,
+            event_consumer,
+            event_producer,
+            Some(state),
+        )
+    }
+
+    fn build<R: Read>(
+        writer: W,
+        reader: R,
+        response_queue: &'a mut Queue<Frame<Response>, 2>,
+        event_consumer: EventConsumer<'a, EVENTS>,
+        event_producer: EventProducer<'a, EVENTS>,
+        state: Option<&'a SharedState>,
+    ) -> (Self, Ingress<'a, R, NoTimer, EVENTS>) {
+        let (response_producer, response_consumer) = response_queue.split();
 
         (
             Self {
                 serial: writer,
                 response: response_consumer,
                 event: event_consumer,
+                timer: NoTimer,
+                guard_time: None,
+                guard_pending: false,
+                awake: true,
+                timeouts: LinearMap::new(),
+                default_timeout: None,
+                busy_retries: 0,
+                busy_delay: Duration::ZERO,
+                write_timeout: None,
+                in_flight: false,
+                discard_stale: false,
+                orphaned: false,
+                acks: true,
+                duty_cycle: None,
+                state,
             },
-            Ingress::<'a> {
+            Ingress {
                 serial: reader,
                 response: response_producer,
                 event: event_producer,
+                timer: NoTimer,
+                timeout: None,
+                resync_limit: None,
+                split_rssi: false,
+                assembler: FrameAssembler::new(),
+                dropped: 0,
+                guard: IngressGuard(state),
             },
         )
     }
+}
 
-    /// Poll until an event is received.
-    pub async fn poll_event(&mut self) -> Frame<Event> {
-        poll_fn(|cx| {
-            if let Some(event) = self.event.dequeue() {
-                Poll::Ready(event)
-            } else {
-                cx.waker().wake_by_ref();
-                Poll::Pending
-            }
-        })
-        .await
+impl<'a, W, const EVENTS: usize> Radio<'a, W, NoTimer, EVENTS>
+where
+    W: Write,
+{
+    /// Use the given timer for delays and timeouts.
+    ///
+    /// This enables response timeouts, using [`Request::default_timeout`] unless overridden with
+    /// [`Radio::with_response_timeout`].
+    pub fn with_timer<U: Timer>(self, timer: U) -> Radio<'a, W, U, EVENTS> {
+        Radio {
+            serial: self.serial,
+            response: self.response,
+            event: self.event,
+            timer,
+            guard_time: self.guard_time,
+            guard_pending: self.guard_pending,
+            awake: self.awake,
+            timeouts: self.timeouts,
+            default_timeout: self.default_timeout,
+            busy_retries: self.busy_retries,
+            busy_delay: self.busy_delay,
+            write_timeout: self.write_timeout,
+            in_flight: self.in_flight,
+            discard_stale: self.discard_stale,
+            orphaned: self.orphaned,
+            acks: self.acks,
+            // without a timer there's no clock, so no duty cycle limit can have been set.
+            duty_cycle: None,
+            state: self.state,
+        }
     }
+}
 
-    /// Send data command.
+impl<'a, W, T, const EVENTS: usize> Radio<'a, W, T, EVENTS>
+where
+    W: Write,
+    T: MaybeTimer,
+{
+    /// Override the time to wait for the response to a specific request.
+    pub fn with_response_timeout(mut self, request: Request, timeout: Duration) -> Self {
+        self.set_response_timeout(request, timeout);
+        self
+    }
+
+    /// Override the time to wait for the response to a specific request.
+    pub fn set_response_timeout(&mut self, request: Request, timeout: Duration) {
+        // capacity covers every request, so this can't fail.
+        self.timeouts.insert(request, timeout).ok();
+    }
+
+    /// Time to wait for the response to any request without a specific override.
+    ///
+    /// Replaces the per-request [`Request::default_timeout`].
+    pub fn with_default_response_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Time to wait for the response to a request.
+    pub fn response_timeout(&self, request: Request) -> Duration {
+        self.timeouts
+            .get(&request)
+            .copied()
+            .or(self.default_timeout)
+            .unwrap_or(request.default_timeout())
+    }
+
+    /// Limit transmit air time, timing sends with the timer, which must also be a [`Clock`].
     ///
-    /// Panics if the data length is larger than 220 (the maximum allowed payload).
-    pub async fn send(&mut self, data: &[u8]) -> Result<(), Error<SendDataError, W::Error>> {
-        assert!(data.len() <= 220);
+    /// Sends which would exceed the budget return [`CommandError::DutyCycleExceeded`] without
+    /// being written. Air time is accounted once the frame has been written, whatever the
+    /// module's status, and once per send however often a busy module is retried.
+    pub fn with_duty_cycle(mut self, duty_cycle: DutyCycle) -> Self
+    where
+        T: Clock,
+    {
+        self.duty_cycle = Some((duty_cycle, T::now));
+        self
+    }
 
-        let mut buf = [0; 224];
-        let size = command(&mut buf, Request::SendData, data);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Air time used in the current duty cycle window, see [`Radio::with_duty_cycle`].
+    pub fn duty_cycle_used(&self) -> Option<Duration> {
+        self.duty_cycle.map(|(duty_cycle, _)| duty_cycle.used())
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Discard any queued response before writing each command.
+    ///
+    /// A response that arrives after its command was cancelled stays queued, and would otherwise
+    /// be taken as the reply to the next command. See also [`Radio::clear_transaction`].
+    pub fn with_stale_response_discard(mut self) -> Self {
+        self.discard_stale = true;
+        self
+    }
 
-        if status == 0 {
-            Ok(())
-        } else {
-            Err(Error::Status(status.into()))
-        }
+    /// Poll until an event is received.
+    ///
+    /// This is cancellation safe: an event is only dequeued when the future completes, so
+    /// dropping it while pending (for example in a `select!` against a timer) never loses an
+    /// event. Events aren't affected by [`CommandError::IngressGone`], so this waits forever
+    /// once the ingress has been dropped.
+    pub async fn poll_event(&mut self) -> Frame<Event> {
+        dequeue(&mut self.event, self.state).await
     }
 
-    /// Performs a soft-reset of the radio module.
+    /// Poll until an event is received, then take as many queued events as fit in `buf`.
     ///
-    /// Returns [`Ok`] once the reset has been confirmed by the device.
-    pub async fn reset(&mut self) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::Reset, &[]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Returns the number of events written to the start of `buf`, which is only zero if `buf`
+    /// is empty. Like [`Radio::poll_event`] this is cancellation safe.
+    pub async fn poll_events_into(&mut self, buf: &mut [Frame<Event>]) -> usize {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return 0;
+        };
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+        *first = dequeue(&mut self.event, self.state).await;
 
-        if status == 0 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
+        let mut count = 1;
+        for slot in rest {
+            let Some(event) = self.event.dequeue() else {
+                break;
+            };
+            *slot = event;
+            count += 1;
         }
+
+        count
     }
 
-    /// Performs a factory reset of the radio module.
-    pub async fn factory_reset(&mut self) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::FactoryReset, &[]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Take the next event if one has been received, without waiting.
+    pub fn try_poll_event(&mut self) -> Option<Frame<Event>> {
+        self.event.dequeue()
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Take the next response if one has been received, without waiting.
+    ///
+    /// Responses are normally consumed by the command awaiting them, so this is only useful for
+    /// discarding a late response, for example after [`Radio::clear_transaction`].
+    pub fn try_poll_response(&mut self) -> Option<Frame<Response>> {
+        self.response.dequeue()
+    }
 
-        if status == 0 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
+    /// Discard all events currently queued, returning how many were discarded.
+    pub fn drain_events(&mut self) -> usize {
+        let mut count = 0;
+        while self.event.dequeue().is_some() {
+            count += 1;
         }
+
+        count
     }
 
-    /// Enters the radio into standby mode.
+    /// Number of events waiting to be polled.
     ///
-    /// Returns [`Ok`] confirming the device will enter standby.
-    pub async fn standby(&mut self) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::Standby, &[]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// The event queue holds at most `EVENTS - 1` events, 15 by default, further events are
+    /// dropped by the [`Ingress`]. By default the newest events are lost when the queue is full,
+    /// [`OverflowPolicy::DropOldest`] keeps them instead by evicting the oldest. Where the
+    /// latest events matter most, either select that policy or take the queued ones in bulk with
+    /// [`Radio::poll_events_into`] to make room.
+    pub fn event_len(&self) -> usize {
+        self.event.len()
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Number of responses waiting to be received.
+    pub fn response_len(&self) -> usize {
+        self.response.len()
+    }
 
-        if status == 0 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
-        }
+    /// Number of frames the [`Ingress`] has dropped because their queue was full.
+    ///
+    /// Only available for a radio created with [`Radio::from_storage`], otherwise see
+    /// [`Ingress::dropped_frames`].
+    pub fn dropped_frames(&self) -> Option<usize> {
+        self.state.map(SharedState::dropped_frames)
     }
 
-    /// Gets the receive signal strength (RSSI) of the last packet received.
-    pub async fn rssi(&mut self) -> Result<u8, Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::Rssi, &[]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Received events as a [`Stream`](futures_core::Stream).
+    #[cfg(feature = "futures")]
+    pub fn poll_events(&mut self) -> EventStream<'_, 'a, W, T, EVENTS> {
+        EventStream::new(self)
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Send data command.
+    ///
+    /// Data longer than [`MAX_DATA_LEN`] (the maximum allowed payload) is rejected with
+    /// [`SendDataError::PayloadInvalid`] without being written.
+    ///
+    /// With listen before talk enabled (see [`Radio::set_lbt`]) [`SendDataError::ChannelBusy`] is
+    /// an expected result on a busy channel, the send should be retried after a short random
+    /// backoff. [`SendDataError::ModuleBusy`] is retried if enabled with
+    /// [`Radio::with_busy_retry`].
+    ///
+    /// With acknowledgements disabled (see [`Radio::acks_enabled`]) the send succeeds once the
+    /// module has accepted the packet, and [`SendDataError::AckTimeout`] is never returned.
+    pub async fn send(&mut self, data: &[u8]) -> Result<(), CommandError<W::Error>> {
+        if data.len() > MAX_DATA_LEN {
+            return Err(CommandError::Send(SendDataError::PayloadInvalid));
+        }
 
-        Ok(status)
+        self.send_request(Request::SendData, data, data.len()).await
     }
 
-    /// Set the transmit power.
+    /// Send data to an explicit destination, instead of the default destination configured with
+    /// [`Radio::destination_net`] and [`Radio::destination_address`].
     ///
-    /// A value outisde the allowable range will result in an error response.
-    pub async fn tx_power(&mut self, power: u8) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::TransmitPower, &[power]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// `mode` is the module's configured address mode, which decides the layout: the data is
+    /// prefixed with the destination network id (if the mode includes one), then the destination
+    /// address (one or two bytes, little endian), as required by the module. `net_id` is unused
+    /// in [`AddressMode::Address`].
+    ///
+    /// Fails with [`CommandError::InvalidAddress`] in [`AddressMode::None`], or if `address`
+    /// isn't the size used by `mode`. Data longer than [`AddressMode::max_data_len`] is rejected
+    /// with [`SendDataError::PayloadInvalid`]. Other errors are reported as for [`Radio::send`].
+    pub async fn send_to(
+        &mut self,
+        mode: AddressMode,
+        net_id: u8,
+        address: Address,
+        data: &[u8],
+    ) -> Result<(), CommandError<W::Error>> {
+        let address_len = match address {
+            Address::Short(_) => 1,
+            Address::Long(_) => 2,
+        };
+        if mode == AddressMode::None || address_len != mode.address_len() {
+            return Err(CommandError::InvalidAddress);
+        }
+        if data.len() > mode.max_data_len() {
+            return Err(CommandError::Send(SendDataError::PayloadInvalid));
+        }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+        let mut header = [net_id, 0, 0];
+        let start = match mode {
+            AddressMode::Address => 1,
+            _ => 0,
+        };
+        address.encode(&mut header[1..]);
 
-        if status == power {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
-        }
+        let mut payload = Vec::<u8, MAX_DATA_LEN>::new();
+        // the length was checked above, so this can't fail.
+        payload
+            .extend_from_slice(&header[start..1 + address_len])
+            .ok();
+        payload.extend_from_slice(data).ok();
+
+        self.send_request(Request::SendDataEx, &payload, data.len())
+            .await
     }
 
-    /// Set the channel.
-    pub async fn channel(&mut self, channel: u8) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::SetChannel, &[channel]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Send a data request, accounting `len` bytes of data against the duty cycle.
+    async fn send_request(
+        &mut self,
+        request: Request,
+        payload: &[u8],
+        len: usize,
+    ) -> Result<(), CommandError<W::Error>> {
+        if let Some((duty_cycle, now)) = &self.duty_cycle {
+            if !duty_cycle.fits(now(&self.timer), len) {
+                return Err(CommandError::DutyCycleExceeded);
+            }
+        }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+        // the air time is only used once the frame has been written, and only once for retries.
+        let mut reserved = false;
+        let timeout = self.response_timeout(request);
+        let acks = self.acks;
+        let mut retries = self.busy_retries;
+        loop {
+            let response = self
+                .request_with(request, payload, timeout, |radio| {
+                    if let Some((duty_cycle, now)) = radio.duty_cycle.as_mut().filter(|_| !reserved)
+                    {
+                        // nothing else was sent since the check, so this still fits.
+                        duty_cycle.reserve(now(&radio.timer), len);
+                    }
+                    reserved = true;
+                })
+                .await;
 
-        if status == channel {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
+            let result = response.and_then(|response| match response.data.first() {
+                Some(0x00) => Ok(()),
+                Some(&status) => match SendDataError::from(status) {
+                    // nothing was waiting for an acknowledgement, so the packet went out.
+                    SendDataError::AckTimeout if !acks => Ok(()),
+                    error => Err(CommandError::Send(error)),
+                },
+                None => Err(CommandError::UnexpectedResponse),
+            });
+
+            match result {
+                Err(CommandError::Send(SendDataError::ModuleBusy)) if retries > 0 => {
+                    retries -= 1;
+                    self.timer.delay_or_pending(self.busy_delay).await;
+                }
+                result => return result,
+            }
         }
     }
 
-    /// Set destination net ID.
-    pub async fn destination_net(&mut self, id: u8) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::SetDestinationNetworkId, &[id]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Performs a soft-reset of the radio module.
+    ///
+    /// Returns [`Ok`] once the reset has been confirmed by the device.
+    ///
+    /// The module can't reload its user settings without restarting, so a reset is also the
+    /// only way to discard volatile changes such as [`Radio::channel`] or [`Radio::tx_power`].
+    pub async fn reset(&mut self) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::Reset, &[], status_ok).await
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Performs a factory reset of the radio module.
+    pub async fn factory_reset(&mut self) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::FactoryReset, &[], status_ok)
+            .await
+    }
 
-        if status == 0x00 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
-        }
+    /// Performs a soft-reset of the radio module and waits until it has restarted.
+    pub async fn reset_and_wait(&mut self) -> Result<(), CommandError<W::Error>> {
+        self.reset().await?;
+        self.wait_for_wakeup().await;
+
+        Ok(())
     }
 
-    /// Set destination address.
-    pub async fn destination_address(&mut self, address: u8) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(
-            &mut buf,
-            command::Request::SetDestinationAddress,
-            &[address],
-        );
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Enters the radio into standby mode.
+    ///
+    /// Returns [`Ok`] confirming the device will enter standby. Further commands return
+    /// [`CommandError::NotAwake`] until [`Radio::wait_for_wakeup`] has seen the module wake up.
+    ///
+    /// The module has no command to sleep for a fixed duration, so it stays in standby until
+    /// woken through its wake-up pin.
+    pub async fn standby(&mut self) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::Standby, &[], status_ok).await?;
+        self.awake = false;
+
+        Ok(())
+    }
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+    /// Enters the radio into shutdown mode.
+    ///
+    /// Returns [`Ok`] confirming the device will shut down. Further commands return
+    /// [`CommandError::NotAwake`] until [`Radio::wait_for_wakeup`] has seen the module restart.
+    pub async fn shutdown(&mut self) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::Shutdown, &[], status_ok).await?;
+        self.awake = false;
 
-        if status == 0x00 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
-        }
+        Ok(())
     }
 
-    /// Set operating mode.
+    /// Quiesce the module before exit.
     ///
-    /// The mode change is performed after the achnoledge response is transmitted.
-    pub async fn mode(&mut self, mode: Mode) -> Result<(), Error<(), W::Error>> {
-        let mut buf = [0; 224];
-        let size = command(&mut buf, command::Request::SetMode, &[mode as u8]);
-        self.serial.write(&buf[..size]).await.map_err(Error::Io)?;
+    /// Flushes pending writes and shuts the module down, then returns the serial writer so the
+    /// port can be released.
+    pub async fn shutdown_gracefully(mut self) -> Result<W, CommandError<W::Error>> {
+        self.serial.flush().await.map_err(CommandError::Io)?;
+        self.shutdown().await?;
 
-        let response = self.poll_response().await;
-        let status = response.data[0];
+        Ok(self.serial)
+    }
 
-        if status == 0x00 {
-            Ok(())
-        } else {
-            Err(Error::Status(()))
+    /// Wait until the module reports it has woken up or restarted.
+    ///
+    /// Any other events received in the mean time are discarded.
+    pub async fn wait_for_wakeup(&mut self) {
+        loop {
+            let event = self.poll_event().await;
+            if let Event::Wakeup | Event::Reset = event.command() {
+                break;
+            }
         }
+
+        self.awake = true;
     }
 
-    /// Poll until a response frame is received through the response channel.
-    async fn poll_response(&mut self) -> Frame<Response> {
-        poll_fn(|cx| {
-            if let Some(response) = self.response.dequeue() {
-                Poll::Ready(response)
-            } else {
-                cx.waker().wake_by_ref();
-                Poll::Pending
-            }
-        })
-        .await
+    /// Forget about a command which was cancelled before its response arrived.
+    ///
+    /// The next command discards a late response to the cancelled command, unless it is of the
+    /// same kind as its own response, for example two sends, which can't be told apart.
+    pub fn clear_transaction(&mut self) {
+        self.orphaned |= self.in_flight;
+        self.in_flight = false;
     }
-}
 
-/// Incomming data handler.
-pub struct Ingress<'a, S>
-where
-    S: Read,
-{
-    serial: S,
-    response: Producer<'a, Frame<Response>, 2>,
-    event: Producer<'a, Frame<Event>, 16>,
-}
+    /// Returns true while a command is waiting for its response.
+    ///
+    /// Stays true after a cancelled command until [`Radio::clear_transaction`] is called.
+    pub fn is_awaiting_response(&self) -> bool {
+        self.in_flight
+    }
 
-impl<'a, S> Ingress<'a, S>
-where
-    S: Read,
-{
-    pub async fn ingest(&mut self) -> Result<(), IngestError> {
-        loop {
-            let mut buf = [0; 3];
-            self.serial.read_exact(&mut buf).await.ok();
+    /// Returns false if the module is known to be in standby or shutdown.
+    pub fn is_awake(&self) -> bool {
+        self.awake
+    }
 
-            if buf[0] != START {
-                continue;
-            }
+    /// Gets the receive signal strength (RSSI) of the last packet received.
+    ///
+    /// The module has no command for the channel's current signal strength or noise floor, so
+    /// this only reflects packets which were actually received. The raw reading is converted to
+    /// dBm with [`rssi_dbm`].
+    pub async fn rssi(&mut self) -> Result<u8, CommandError<W::Error>> {
+        let response = self.request(Request::Rssi, &[]).await?;
 
-            let cmd = buf[1];
-            let len = buf[2] as usize;
+        response
+            .data
+            .first()
+            .copied()
+            .ok_or(CommandError::UnexpectedResponse)
+    }
 
-            if len > MAX_PAYLOAD_LEN {
-                return Err(IngestError::PayloadLength);
-            }
+    /// Set the transmit power.
+    ///
+    /// A value outisde the allowable range will result in an error response.
+    pub async fn tx_power(&mut self, power: u8) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::TransmitPower, &[power], status_echo(power))
+            .await
+    }
 
-            let mut payload = Vec::<u8, MAX_PAYLOAD_LEN>::new();
-            unsafe { payload.set_len(len) };
-            self.serial.read_exact(&mut payload[0..len]).await.ok();
+    /// Set the channel.
+    pub async fn channel(&mut self, channel: Channel) -> Result<(), CommandError<W::Error>> {
+        let channel = channel.get();
+        self.request_ack(Request::SetChannel, &[channel], status_echo(channel))
+            .await
+    }
 
-            let mut _checksum = [0; 1];
-            self.serial.read_exact(&mut buf).await.ok();
+    /// Set destination net ID.
+    pub async fn destination_net(&mut self, id: u8) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::SetDestinationNetworkId, &[id], status_ok)
+            .await
+    }
 
-            //todo: check checksum
+    /// Set destination address.
+    pub async fn destination_address(&mut self, address: u8) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::SetDestinationAddress, &[address], status_ok)
+            .await
+    }
 
-            if let Some(event) = Event::try_from_raw(cmd) {
-                self.event
-                    .enqueue(Frame::<Event> {
-                        command: event,
-                        data: payload,
-                    })
-                    .ok();
-                continue;
-            }
+    /// Set destination net ID and address.
+    ///
+    /// The module has no command to set both at once, so this issues the two setters back to
+    /// back. As the radio is borrowed mutably throughout no send can be issued in between, but
+    /// if the second command fails the destination is left half-updated.
+    pub async fn set_destination(
+        &mut self,
+        net: u8,
+        address: u8,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.destination_net(net).await?;
+        self.destination_address(address).await
+    }
 
-            if let Some(response) = Response::try_from_raw(cmd) {
-                self.response
-                    .enqueue(Frame::<Response> {
-                        command: response,
-                        data: payload,
-                    })
-                    .ok();
-                continue;
-            }
-        }
+    /// Set the channel and destination, ready to send.
+    ///
+    /// Stops at the first step which fails, leaving the following settings unchanged.
+    pub async fn configure(
+        &mut self,
+        net: u8,
+        address: u8,
+        channel: Channel,
+    ) -> Result<(), ConfigureError<W::Error>> {
+        let fail = |step| move |error| ConfigureError { step, error };
+
+        self.channel(channel)
+            .await
+            .map_err(fail(ConfigureStep::Channel))?;
+        self.destination_net(net)
+            .await
+            .map_err(fail(ConfigureStep::DestinationNet))?;
+        self.destination_address(address)
+            .await
+            .map_err(fail(ConfigureStep::DestinationAddress))
     }
-}
 
-/// Ingest error.
-#[derive(Debug, Clone, Copy)]
-pub enum IngestError {
-    /// Start byte was not correct.
-    StartByte,
-    /// Payload length is too long.
-    PayloadLength,
-    /// Command id is not recognised.
-    UnknownCommand,
+    /// Get destination net ID and address from the live configuration.
+    pub async fn destination(&mut self) -> Result<(u8, u8), CommandError<W::Error>> {
+        let settings = self.runtime_settings().await?;
+        Ok((settings.destination_net, settings.destination_address))
+    }
+
+    /// Set operating mode.
+    ///
+    /// The mode change is performed after the achnoledge response is transmitted. For a radio
+    /// created with [`Radio::from_storage`] the paired [`Ingress`] follows the change, see
+    /// [`IngestError::Transparent`].
+    ///
+    /// The module has no command to read back its current mode, so the acknowledgement is the
+    /// only confirmation of the change.
+    pub async fn mode(&mut self, mode: Mode) -> Result<(), CommandError<W::Error>> {
+        self.request_ack(Request::SetMode, &[mode.to_raw()], status_ok)
+            .await?;
+        self.assume_mode(mode);
+
+        Ok(())
+    }
+
+    /// Record a mode change made without a command, for example using the module's mode pin.
+    ///
+    /// Only needed for a radio created with [`Radio::from_storage`], so the paired [`Ingress`]
+    /// follows the change.
+    pub fn assume_mode(&mut self, mode: Mode) {
+        if let Some(state) = self.state {
+            state.set_transparent(matches!(mode, Mode::Transparent));
+        }
+    }
+
+    /// Change a user setting.
+    ///
+    /// User settings are stored in non-volatile memory and most only take effect after a reset.
+    ///
+    /// Returns [`CommandError::InvalidSetting`] without writing if the setting is read only or
+    /// the value has the wrong length.
+    pub async fn set_user_setting(
+        &mut self,
+        setting: Setting,
+        value: &[u8],
+    ) -> Result<(), CommandError<W::Error>> {
+        if setting.value_len() != Some(value.len()) {
+            return Err(CommandError::InvalidSetting);
+        }
+
+        let mut data = Vec::<u8, MAX_PAYLOAD_LEN>::new();
+        data.push(setting as u8).ok();
+        // setting values are at most a few bytes, so this always fits.
+        data.extend_from_slice(value).ok();
+
+        self.request_ack(Request::SetUserSetting, &data, status_ok)
+            .await
+    }
+
+    /// Read a user setting.
+    ///
+    /// Returns the raw setting value.
+    pub async fn get_user_setting(
+        &mut self,
+        setting: Setting,
+    ) -> Result<Vec<u8, MAX_PAYLOAD_LEN>, CommandError<W::Error>> {
+        let timeout = self.response_timeout(Request::GetUserSetting);
+        self.get_setting_raw(&[setting as u8], timeout).await
+    }
+
+    /// Read a little endian `u16` user setting.
+    pub async fn get_user_setting_u16(
+        &mut self,
+        setting: Setting,
+    ) -> Result<u16, CommandError<W::Error>> {
+        let value = self.get_user_setting(setting).await?;
+
+        le_u16(&value).ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Read a little endian `u32` user setting.
+    pub async fn get_user_setting_u32(
+        &mut self,
+        setting: Setting,
+    ) -> Result<u32, CommandError<W::Error>> {
+        let value = self.get_user_setting(setting).await?;
+
+        le_u32(&value).ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Read the module serial number from the factory settings.
+    pub async fn serial_number(&mut self) -> Result<u32, CommandError<W::Error>> {
+        self.get_user_setting_u32(Setting::FactorySettings).await
+    }
+
+    /// Read a contiguous block of user settings in a single request.
+    ///
+    /// Returns the raw block of `len` bytes starting at `start`. Only supported by firmware which
+    /// accepts a length argument, other firmware will respond with an error status.
+    pub async fn get_user_settings(
+        &mut self,
+        start: Setting,
+        len: u8,
+    ) -> Result<Vec<u8, MAX_PAYLOAD_LEN>, CommandError<W::Error>> {
+        let timeout = self.response_timeout(Request::GetUserSetting);
+        self.get_setting_raw(&[start as u8, len], timeout).await
+    }
+
+    /// Set the default RF profile.
+    ///
+    /// Takes effect after the next reset.
+    pub async fn set_rf_profile(
+        &mut self,
+        profile: RfProfile,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::DefaultRfProfile, &[profile as u8])
+            .await
+    }
+
+    /// Get the default RF profile.
+    pub async fn rf_profile(&mut self) -> Result<RfProfile, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::DefaultRfProfile).await?;
+
+        value
+            .first()
+            .and_then(|&raw| RfProfile::try_from_raw(raw))
+            .ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Set the default address mode.
+    ///
+    /// Takes effect after the next reset.
+    pub async fn set_address_mode(
+        &mut self,
+        mode: AddressMode,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::DefaultAddressMode, &[mode as u8])
+            .await
+    }
+
+    /// Get the default address mode.
+    pub async fn address_mode(&mut self) -> Result<AddressMode, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::DefaultAddressMode).await?;
+
+        value
+            .first()
+            .and_then(|&raw| AddressMode::try_from_raw(raw))
+            .ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Set the source address.
+    ///
+    /// A long address is stored in [`Setting::SourceAddr`] and [`Setting::SourceAddrMsb`], a
+    /// short address leaves the high byte unchanged. Takes effect after the next reset.
+    pub async fn set_source_address(
+        &mut self,
+        address: Address,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_address_setting(Setting::SourceAddr, Setting::SourceAddrMsb, address)
+            .await
+    }
+
+    /// Get the source address.
+    ///
+    /// Read as a long address if `mode` is [`AddressMode::NetworkAndLongAddress`], otherwise as a
+    /// short address.
+    pub async fn source_address(
+        &mut self,
+        mode: AddressMode,
+    ) -> Result<Address, CommandError<W::Error>> {
+        self.address_setting(Setting::SourceAddr, Setting::SourceAddrMsb, mode)
+            .await
+    }
+
+    /// Set the default destination address.
+    ///
+    /// A long address is stored in [`Setting::DefaultDestinationAddr`] and
+    /// [`Setting::DefaultDestinationAddrMsb`], a short address leaves the high byte unchanged.
+    /// Takes effect after the next reset.
+    pub async fn set_default_destination_address(
+        &mut self,
+        address: Address,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_address_setting(
+            Setting::DefaultDestinationAddr,
+            Setting::DefaultDestinationAddrMsb,
+            address,
+        )
+        .await
+    }
+
+    /// Get the default destination address.
+    ///
+    /// Read as a long address if `mode` is [`AddressMode::NetworkAndLongAddress`], otherwise as a
+    /// short address.
+    pub async fn default_destination_address(
+        &mut self,
+        mode: AddressMode,
+    ) -> Result<Address, CommandError<W::Error>> {
+        self.address_setting(
+            Setting::DefaultDestinationAddr,
+            Setting::DefaultDestinationAddrMsb,
+            mode,
+        )
+        .await
+    }
+
+    /// Write an address split over a low and high byte setting.
+    async fn set_address_setting(
+        &mut self,
+        lsb: Setting,
+        msb: Setting,
+        address: Address,
+    ) -> Result<(), CommandError<W::Error>> {
+        match address {
+            Address::Short(address) => self.set_user_setting(lsb, &[address]).await,
+            Address::Long(address) => {
+                let [low, high] = address.to_le_bytes();
+                self.set_user_setting(lsb, &[low]).await?;
+                self.set_user_setting(msb, &[high]).await
+            }
+        }
+    }
+
+    /// Read an address split over a low and high byte setting.
+    ///
+    /// The high byte is only read if `mode` uses long addresses.
+    async fn address_setting(
+        &mut self,
+        lsb: Setting,
+        msb: Setting,
+        mode: AddressMode,
+    ) -> Result<Address, CommandError<W::Error>> {
+        let low = self.get_user_setting(lsb).await?;
+        let low = *low.first().ok_or(CommandError::UnexpectedResponse)?;
+        if mode.address_len() < 2 {
+            return Ok(Address::Short(low));
+        }
+
+        let high = self.get_user_setting(msb).await?;
+        let high = *high.first().ok_or(CommandError::UnexpectedResponse)?;
+
+        Ok(Address::Long(u16::from_le_bytes([low, high])))
+    }
+
+    /// Set the configuration flags.
+    ///
+    /// Takes effect after the next reset.
+    pub async fn set_config_flags(
+        &mut self,
+        flags: ConfigFlags,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::ConfigFlags, &flags.bits().to_le_bytes())
+            .await
+    }
+
+    /// Get the configuration flags.
+    pub async fn config_flags(&mut self) -> Result<ConfigFlags, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::ConfigFlags).await?;
+
+        le_u16(&value)
+            .map(ConfigFlags::from_bits)
+            .ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Enable or disable listen before talk.
+    ///
+    /// Updates only the listen before talk flag, leaving the other configuration flags as they
+    /// are. Takes effect after the next reset.
+    pub async fn set_lbt(&mut self, enabled: bool) -> Result<(), CommandError<W::Error>> {
+        let flags = self.config_flags().await?;
+
+        self.set_config_flags(flags.set(ConfigFlags::LISTEN_BEFORE_TALK, enabled))
+            .await
+    }
+
+    /// Set the number of MAC retries.
+    ///
+    /// The module only requests an acknowledgement for packets it can retry, so zero retries
+    /// also disables acknowledgements, see [`Radio::acks_enabled`]. Takes effect after the next
+    /// reset.
+    pub async fn set_mac_retries(&mut self, retries: u8) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::RetryNumbers, &[retries])
+            .await?;
+        self.acks = retries > 0;
+
+        Ok(())
+    }
+
+    /// Get the number of MAC retries, updating [`Radio::acks_enabled`] to match.
+    pub async fn mac_retries(&mut self) -> Result<u8, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::RetryNumbers).await?;
+        let retries = value
+            .first()
+            .copied()
+            .ok_or(CommandError::UnexpectedResponse)?;
+        self.acks = retries > 0;
+
+        Ok(retries)
+    }
+
+    /// Returns true if sent packets are acknowledged, which decides how [`Radio::send`]
+    /// interprets its result.
+    ///
+    /// Assumed until the configuration is read or written with [`Radio::mac_retries`] or
+    /// [`Radio::set_mac_retries`].
+    pub fn acks_enabled(&self) -> bool {
+        self.acks
+    }
+
+    /// Record the acknowledgement configuration without a command, for example when the module
+    /// was configured by another tool.
+    pub fn assume_acks(&mut self, enabled: bool) {
+        self.acks = enabled;
+    }
+
+    /// Set the number of repeater slots.
+    ///
+    /// Takes effect after the next reset.
+    pub async fn set_repeater_slots(&mut self, slots: u8) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::RpNumSlots, &[slots]).await
+    }
+
+    /// Get the number of repeater slots.
+    pub async fn repeater_slots(&mut self) -> Result<u8, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::RpNumSlots).await?;
+
+        value
+            .first()
+            .copied()
+            .ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Set the repeater flags.
+    ///
+    /// Takes effect after the next reset.
+    pub async fn set_repeater_flags(
+        &mut self,
+        flags: RepeaterFlags,
+    ) -> Result<(), CommandError<W::Error>> {
+        self.set_user_setting(Setting::RpFlags, &[flags.bits()])
+            .await
+    }
+
+    /// Get the repeater flags.
+    pub async fn repeater_flags(&mut self) -> Result<RepeaterFlags, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::RpFlags).await?;
+
+        value
+            .first()
+            .map(|&bits| RepeaterFlags::from_bits(bits))
+            .ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Read the live configuration of the module in a single request.
+    pub async fn runtime_settings(&mut self) -> Result<RuntimeSettings, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::RuntimeSettings).await?;
+
+        RuntimeSettings::from_raw(&value).ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Read the firmware version.
+    pub async fn firmware_version(&mut self) -> Result<FirmwareVersion, CommandError<W::Error>> {
+        let value = self.get_user_setting(Setting::FirmwareVersion).await?;
+
+        FirmwareVersion::from_raw(&value).ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Check the module is alive and responding, returning its firmware version.
+    ///
+    /// Intended for startup, this fails fast with [`CommandError::Timeout`] once
+    /// [`READY_TIMEOUT`] has passed, whatever the [response timeout](Radio::response_timeout).
+    /// Timeouts need a timer, see [`Radio::with_timer`], without one this waits until the module
+    /// responds and can't fail fast.
+    pub async fn ready(&mut self) -> Result<FirmwareVersion, CommandError<W::Error>> {
+        let value = self
+            .get_setting_raw(&[Setting::FirmwareVersion as u8], READY_TIMEOUT)
+            .await?;
+
+        FirmwareVersion::from_raw(&value).ok_or(CommandError::UnexpectedResponse)
+    }
+
+    /// Send a command which isn't otherwise supported, returning its raw response.
+    ///
+    /// The start byte, length and checksum are added to the frame, and the response is awaited
+    /// as for any other command using the [default response
+    /// timeout](Radio::with_default_response_timeout). The first response received is returned
+    /// whatever its command. Only responses known as a [`Response`] can be received, others are
+    /// discarded by the [`Ingress`] with [`DecodeError::UnknownCommand`] so the wait times out.
+    ///
+    /// Panics if the data length is larger than [`MAX_PAYLOAD_LEN`].
+    pub async fn send_raw(
+        &mut self,
+        cmd: u8,
+        data: &[u8],
+    ) -> Result<Frame<Response>, CommandError<W::Error>> {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let size = command(&mut buf, cmd, data);
+        trace!("tx raw {:#04x}: {}", cmd, Hex(&buf[..size]));
+
+        let timeout = self.default_timeout.unwrap_or(DEFAULT_TIMEOUT);
+        self.transact(&buf[..size], timeout, None, |_| ()).await
+    }
+
+    /// Issue a get user setting request, returning the setting data on success.
+    async fn get_setting_raw(
+        &mut self,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<u8, MAX_PAYLOAD_LEN>, CommandError<W::Error>> {
+        let response = self
+            .request_with(Request::GetUserSetting, data, timeout, |_| ())
+            .await?;
+
+        match response.data.first() {
+            Some(0x00) => Ok(Vec::from_slice(&response.data[1..]).unwrap()),
+            Some(&status) => Err(CommandError::Status(status)),
+            None => Err(CommandError::UnexpectedResponse),
+        }
+    }
+
+    /// Issue a request and check the status byte of its response.
+    async fn request_ack(
+        &mut self,
+        request: Request,
+        data: &[u8],
+        verify: impl Fn(u8) -> Result<(), CommandError<W::Error>>,
+    ) -> Result<(), CommandError<W::Error>> {
+        let response = self.request(request, data).await?;
+
+        match response.data.first() {
+            Some(&status) => verify(status),
+            None => Err(CommandError::UnexpectedResponse),
+        }
+    }
+
+    /// Issue a request and wait for its response frame.
+    async fn request(
+        &mut self,
+        request: Request,
+        data: &[u8],
+    ) -> Result<Frame<Response>, CommandError<W::Error>> {
+        let timeout = self.response_timeout(request);
+        self.request_with(request, data, timeout, |_| ()).await
+    }
+
+    /// Issue a request, calling `on_write` once it has been written, and wait up to `timeout`
+    /// for its response frame.
+    async fn request_with(
+        &mut self,
+        request: Request,
+        data: &[u8],
+        timeout: Duration,
+        on_write: impl FnOnce(&mut Self),
+    ) -> Result<Frame<Response>, CommandError<W::Error>> {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let size = command(&mut buf, request as u8, data);
+        trace!("tx {:?}: {}", request, Hex(&buf[..size]));
+
+        let response = self
+            .transact(
+                &buf[..size],
+                timeout,
+                request.expected_response(),
+                |radio| {
+                    radio.guard_pending = request.needs_guard_time();
+                    on_write(radio);
+                },
+            )
+            .await;
+
+        // a mismatched response belongs to another command, its status byte means nothing here.
+        let response = response?;
+        if Some(*response.command()) != request.expected_response() {
+            return Err(CommandError::UnexpectedResponse);
+        }
+
+        Ok(response)
+    }
+
+    /// Write a command frame, calling `on_write` once it has been written, and wait for the
+    /// response.
+    ///
+    /// A late response to a cancelled or timed out command is skipped if it doesn't match
+    /// `expected`.
+    async fn transact(
+        &mut self,
+        frame: &[u8],
+        timeout: Duration,
+        expected: Option<Response>,
+        on_write: impl FnOnce(&mut Self),
+    ) -> Result<Frame<Response>, CommandError<W::Error>> {
+        if !self.awake {
+            return Err(CommandError::NotAwake);
+        }
+
+        if self.in_flight {
+            return Err(CommandError::Busy);
+        }
+
+        if let Some(guard_time) = self.guard_time.filter(|_| self.guard_pending) {
+            self.timer.delay_or_pending(guard_time).await;
+        }
+        self.guard_pending = false;
+
+        if self.discard_stale || self.orphaned {
+            while let Some(_stale) = self.response.dequeue() {
+                trace!("discarded stale {:?}", _stale.command());
+                self.orphaned = false;
+            }
+        }
+
+        // flush so buffered writers don't hold the frame back while we wait for the response.
+        self.in_flight = true;
+        let serial = &mut self.serial;
+        let write = async {
+            serial.write_all(frame).await?;
+            serial.flush().await
+        };
+        let written = match self.write_timeout {
+            Some(timeout) => with_timeout(&mut self.timer, timeout, write)
+                .await
+                .map_err(|_| CommandError::Timeout)
+                .and_then(|result| result.map_err(CommandError::Io)),
+            None => write.await.map_err(CommandError::Io),
+        };
+        if let Err(err) = written {
+            self.in_flight = false;
+            return Err(err);
+        }
+        on_write(self);
+
+        let mut response = self.poll_response(timeout).await;
+        if let Ok(frame) = &response {
+            let command = *frame.command();
+            if self.orphaned && expected.is_some_and(|expected| expected != command) {
+                trace!("discarded late {:?}", command);
+                response = self.poll_response(timeout).await;
+            }
+        }
+        self.orphaned = matches!(response, Err(CommandError::Timeout));
+        self.in_flight = false;
+
+        response
+    }
+
+    /// Poll until the response to a request is received through the response channel.
+    async fn poll_response(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Frame<Response>, CommandError<W::Error>> {
+        let state = self.state;
+        with_timeout(
+            &mut self.timer,
+            timeout,
+            dequeue_live(&mut self.response, state),
+        )
+        .await
+        .map_err(|_| CommandError::Timeout)?
+        .ok_or(CommandError::IngressGone)
+    }
+}
+
+impl<'a, W, T, const EVENTS: usize> Radio<'a, W, T, EVENTS>
+where
+    W: Write,
+    T: Timer,
+{
+    /// Bound the time spent writing a command, separately from waiting for its response.
+    ///
+    /// A write which doesn't complete in time (for example on a wedged UART) returns
+    /// [`CommandError::Timeout`]. Requires a timer, see [`Radio::with_timer`].
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Wait at least `guard_time` after a reset or mode change before writing the next command.
+    ///
+    /// The module doesn't accept commands immediately after these, so without a guard time the
+    /// following command may be rejected. Requires a timer, see [`Radio::with_timer`].
+    pub fn with_guard_time(mut self, guard_time: Duration) -> Self {
+        self.guard_time = Some(guard_time);
+        self
+    }
+
+    /// Retry sends rejected with [`SendDataError::ModuleBusy`] up to `retries` times.
+    ///
+    /// The module reports busy while its transmit buffer is full, which clears quickly, so
+    /// [`Radio::send`] waits `delay` and tries again before returning the error.
+    pub fn with_busy_retry(mut self, retries: u8, delay: Duration) -> Self {
+        self.busy_retries = retries;
+        self.busy_delay = delay;
+        self
+    }
+
+    /// Send data, retrying on transient errors.
+    ///
+    /// Makes up to `attempts` sends, waiting `backoff` between each using the radio's timer.
+    /// Errors which aren't [retryable](SendDataError::is_retryable) are returned immediately,
+    /// otherwise the error from the final attempt is returned.
+    pub async fn send_retry(
+        &mut self,
+        data: &[u8],
+        attempts: u8,
+        backoff: Duration,
+    ) -> Result<(), CommandError<W::Error>> {
+        let mut remaining = attempts.max(1);
+
+        loop {
+            remaining -= 1;
+
+            match self.send(data).await {
+                Err(CommandError::Send(err)) if err.is_retryable() && remaining > 0 => {
+                    self.timer.delay(backoff).await
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Performs a hardware reset by pulsing the module's reset line.
+    ///
+    /// Holds the line low for `duration` (requires a timer, see [`Radio::with_timer`]), then
+    /// waits until the module reports it has restarted. This recovers a module which no longer
+    /// responds over the UART. The pin is passed in rather than held by the radio, as it's
+    /// usually shared with other recovery logic.
+    pub async fn hard_reset<P: ResetPin>(
+        &mut self,
+        pin: &mut P,
+        duration: Duration,
+    ) -> Result<(), P::Error> {
+        pin.set_low()?;
+        self.timer.delay(duration).await;
+        pin.set_high()?;
+
+        self.wait_for_wakeup().await;
+
+        Ok(())
+    }
+
+    /// Query the RSSI every `interval`, passing each reading to `f`.
+    ///
+    /// Readings are in dBm, or [`None`] if no packet has been received. Requires a timer, see
+    /// [`Radio::with_timer`]. Only returns if a query fails.
+    pub async fn rssi_continuous(
+        &mut self,
+        interval: Duration,
+        mut f: impl FnMut(Option<i8>),
+    ) -> Result<(), CommandError<W::Error>> {
+        loop {
+            let rssi = self.rssi().await?;
+            f(rssi_dbm(rssi));
+
+            self.timer.delay(interval).await;
+        }
+    }
+
+    /// Sweep the channels in `range`, listening on each for `dwell`.
+    ///
+    /// Returns the strongest RSSI of the packets received on each channel, or [`None`] if none
+    /// were. The module can't measure the noise floor, so only channels with traffic report a
+    /// signal strength. Channels outside [`Channel::MIN`] to [`Channel::MAX`] are skipped.
+    ///
+    /// Events received during the scan are consumed. The original channel is restored afterwards,
+    /// unless a command fails part way. Fails with [`CommandError::UnexpectedResponse`] before
+    /// changing channel if the original channel isn't valid. Requires a timer, see
+    /// [`Radio::with_timer`].
+    pub async fn channel_scan(
+        &mut self,
+        range: RangeInclusive<u8>,
+        dwell: Duration,
+    ) -> Result<Vec<(Channel, Option<i8>), CHANNEL_COUNT>, CommandError<W::Error>> {
+        let original = self.runtime_settings().await?.channel;
+        let original = Channel::new(original).ok_or(CommandError::UnexpectedResponse)?;
+        let mut results = Vec::new();
+        let state = self.state;
+
+        for channel in range.filter_map(Channel::new) {
+            self.channel(channel).await?;
+
+            let mut strongest = None;
+            let listen = async {
+                loop {
+                    if let Some(rssi) = dequeue(&mut self.event, state).await.rssi() {
+                        strongest = strongest.max(Some(rssi));
+                    }
+                }
+            };
+            with_timeout(&mut self.timer, dwell, listen).await.ok();
+
+            // capacity covers every valid channel, so this can't fail.
+            results.push((channel, strongest)).ok();
+        }
+
+        self.channel(original).await?;
+
+        Ok(results)
+    }
+}
+
+/// Poll until a frame is received through the channel.
+///
+/// Waits forever once the producer has been dropped.
+async fn dequeue<T>(consumer: &mut impl Dequeue<T>, state: Option<&SharedState>) -> T {
+    poll_fn(|cx| match poll_dequeue(consumer, state, cx) {
+        Poll::Ready(Some(frame)) => Poll::Ready(frame),
+        _ => Poll::Pending,
+    })
+    .await
+}
+
+/// Receiving end of a frame channel.
+pub(crate) trait Dequeue<T> {
+    fn dequeue(&mut self) -> Option<T>;
+}
+
+impl<T, const N: usize> Dequeue<T> for Consumer<'_, T, N> {
+    fn dequeue(&mut self) -> Option<T> {
+        Consumer::dequeue(self)
+    }
+}
+
+impl<const N: usize> Dequeue<Frame<Event>> for EventConsumer<'_, N> {
+    fn dequeue(&mut self) -> Option<Frame<Event>> {
+        EventConsumer::dequeue(self)
+    }
+}
+
+/// Poll until a frame is received, or return [`None`] once the producer has been dropped.
+async fn dequeue_live<T>(consumer: &mut impl Dequeue<T>, state: Option<&SharedState>) -> Option<T> {
+    poll_fn(|cx| poll_dequeue(consumer, state, cx)).await
+}
+
+/// Take a frame from the channel, or return [`None`] once the producer has been dropped.
+///
+/// With shared state the ingress wakes the task once it queues a frame, otherwise the task is
+/// woken straight away to poll again.
+pub(crate) fn poll_dequeue<T>(
+    consumer: &mut impl Dequeue<T>,
+    state: Option<&SharedState>,
+    cx: &mut core::task::Context<'_>,
+) -> Poll<Option<T>> {
+    if let Some(frame) = consumer.dequeue() {
+        return Poll::Ready(Some(frame));
+    }
+
+    let Some(state) = state else {
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+    };
+
+    state.radio_waker.register(cx.waker());
+    // a frame may have been queued before the waker was registered, or just before the ingress
+    // was dropped.
+    if let Some(frame) = consumer.dequeue() {
+        Poll::Ready(Some(frame))
+    } else if state.ingress_dropped() {
+        Poll::Ready(consumer.dequeue())
+    } else {
+        Poll::Pending
+    }
+}
+
+/// Convert a raw RSSI reading from [`Radio::rssi`] to dBm.
+///
+/// Returns [`None`] for the reading the module gives before any packet has been received.
+pub fn rssi_dbm(raw: u8) -> Option<i8> {
+    (raw != 0x80).then_some(raw as i8)
+}
+
+/// Status check for responses where zero indicates success.
+fn status_ok<IO>(status: u8) -> Result<(), CommandError<IO>> {
+    match status {
+        0x00 => Ok(()),
+        _ => Err(CommandError::Status(status)),
+    }
+}
+
+/// Status check for responses which echo back the requested value on success.
+fn status_echo<IO>(value: u8) -> impl Fn(u8) -> Result<(), CommandError<IO>> {
+    move |status| {
+        if status == value {
+            Ok(())
+        } else {
+            Err(CommandError::Status(status))
+        }
+    }
+}
+
+/// Incomming data handler.
+pub struct Ingress<'a, S, T = NoTimer, const EVENTS: usize = 16>
+where
+    S: Read,
+{
+    serial: S,
+    response: Producer<'a, Frame<Response>, 2>,
+    event: EventProducer<'a, EVENTS>,
+    timer: T,
+    timeout: Option<Duration>,
+    resync_limit: Option<usize>,
+    split_rssi: bool,
+    assembler: FrameAssembler,
+    dropped: usize,
+    guard: IngressGuard<'a>,
+}
+
+impl<'a, S, T, const EVENTS: usize> Ingress<'a, S, T, EVENTS>
+where
+    S: Read,
+    T: MaybeTimer,
+{
+    /// Bound the time spent reading the remainder of a frame once its start byte is received.
+    ///
+    /// If the frame isn't complete within the timeout (for example a corrupt length field) it's
+    /// discarded and the receiver resynchronises on the next start byte.
+    pub fn with_timeout<U: Timer>(self, timer: U, timeout: Duration) -> Ingress<'a, S, U, EVENTS> {
+        Ingress {
+            serial: self.serial,
+            response: self.response,
+            event: self.event,
+            timer,
+            timeout: Some(timeout),
+            resync_limit: self.resync_limit,
+            split_rssi: self.split_rssi,
+            assembler: self.assembler,
+            dropped: self.dropped,
+            guard: self.guard,
+        }
+    }
+
+    /// Give up resynchronising after skipping more than `limit` bytes without finding a start
+    /// byte.
+    ///
+    /// Ingest then returns [`IngestError::StartByte`], so a supervisor can intervene (for example
+    /// power cycling the module) rather than scanning a stuck line forever.
+    pub fn with_resync_limit(mut self, limit: usize) -> Self {
+        self.resync_limit = Some(limit);
+        self
+    }
+
+    /// Split the trailing RSSI byte off received data events.
+    ///
+    /// The data of [`Event::DataReceived`] and [`Event::DataRepeat`] frames is then only the
+    /// received payload, with the signal strength available from [`Frame::rssi`].
+    pub fn with_rssi_split(mut self) -> Self {
+        self.split_rssi = true;
+        self
+    }
+
+    /// Returns true if more bytes than the resync limit have been skipped.
+    fn resync_exceeded(&self, skipped: usize) -> bool {
+        self.resync_limit.is_some_and(|limit| skipped > limit)
+    }
+
+    /// Number of events queued but not yet polled by the radio.
+    pub fn event_len(&self) -> usize {
+        self.event.len()
+    }
+
+    /// Number of responses queued but not yet received by the radio.
+    pub fn response_len(&self) -> usize {
+        self.response.len()
+    }
+
+    /// Number of received frames dropped because the radio's queue for them was full.
+    ///
+    /// Events are dropped once [`Radio::event_len`] reaches its limit, which ones are lost is
+    /// set by the storage's [`OverflowPolicy`]. An evicted event counts as dropped, as does one
+    /// that couldn't be queued because the radio was taking an event at the same time.
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped
+    }
+
+    /// Receive frames, dispatching them to the radio.
+    ///
+    /// Runs until an error occurs. All errors other than [fatal](IngestError::is_fatal) ones are
+    /// recoverable by calling this again, once the module is back in command mode after
+    /// [`IngestError::Transparent`]. [`Ingress::run`] does this until a fatal error occurs.
+    pub async fn ingest(&mut self) -> Result<(), IngestError<S::Error>> {
+        let mut skipped = 0;
+
+        loop {
+            if self.is_transparent() {
+                return Err(IngestError::Transparent);
+            }
+
+            // scan a byte at a time, so a start byte anywhere in the stream is found.
+            let mut start = [0; 1];
+            self.serial
+                .read_exact(&mut start)
+                .await
+                .map_err(read_error)?;
+
+            if start[0] != START {
+                skipped += 1;
+                if self.resync_exceeded(skipped) {
+                    return Err(IngestError::StartByte);
+                }
+                continue;
+            }
+            skipped = 0;
+
+            let mut header = [0; 2];
+            if !self.read_frame(&mut header).await? {
+                continue;
+            }
+
+            let [cmd, len] = header;
+            let len = len as usize;
+
+            if len > MAX_PAYLOAD_LEN {
+                return Err(IngestError::Decode(DecodeError::PayloadLength));
+            }
+
+            #[cfg(not(feature = "alloc"))]
+            let mut payload = Buffer::new();
+            // `len` was checked above, so it never exceeds the capacity of `Buffer`.
+            #[cfg(not(feature = "alloc"))]
+            payload.resize_default(len).ok();
+            #[cfg(feature = "alloc")]
+            let mut payload = alloc::vec![0; len];
+            if !self.read_frame(&mut payload[0..len]).await? {
+                continue;
+            }
+
+            let mut received = [0; 1];
+            if !self.read_frame(&mut received).await? {
+                continue;
+            }
+
+            let mut checksum = ChecksumState::new();
+            checksum.update(&[START, cmd, len as u8]);
+            checksum.update(&payload);
+            if checksum.finish() != received[0] {
+                trace!("rx bad checksum {:#04x}: {}", cmd, Hex(&payload));
+                return Err(IngestError::Decode(DecodeError::Checksum));
+            }
+
+            self.dispatch(cmd, payload)?;
+        }
+    }
+
+    /// Receive frames using plain reads, dispatching them to the radio.
+    ///
+    /// Unlike [`Ingress::ingest`] this never waits for an exact number of bytes, frames are
+    /// reassembled from whatever each read returns. This suits interrupt driven UART drivers
+    /// which deliver a few bytes at a time. A partially received frame is discarded if the rest
+    /// of it doesn't arrive within the timeout set by [`Ingress::with_timeout`].
+    ///
+    /// Errors are the same as for [`Ingress::ingest`].
+    pub async fn ingest_buffered(&mut self) -> Result<(), IngestError<S::Error>> {
+        let mut chunk = [0; 32];
+        let mut skipped = 0;
+
+        loop {
+            if self.is_transparent() {
+                return Err(IngestError::Transparent);
+            }
+
+            let read = self.serial.read(&mut chunk);
+            let result = match self.timeout.filter(|_| !self.assembler.is_idle()) {
+                Some(timeout) => match with_timeout(&mut self.timer, timeout, read).await {
+                    Ok(result) => result,
+                    Err(Timeout) => {
+                        self.assembler.reset();
+                        continue;
+                    }
+                },
+                None => read.await,
+            };
+
+            let len = match result {
+                Ok(0) => return Err(IngestError::Closed),
+                Ok(len) => len,
+                Err(err) if is_closed_kind(err.kind()) => return Err(IngestError::Closed),
+                Err(err) => return Err(IngestError::Io(err)),
+            };
+
+            // finish the chunk before reporting an error, so following frames aren't lost.
+            let mut error = None;
+            for &byte in &chunk[..len] {
+                if self.assembler.is_idle() && byte != START {
+                    skipped += 1;
+                    if self.resync_exceeded(skipped) {
+                        error = error.or(Some(IngestError::StartByte));
+                        skipped = 0;
+                    }
+                } else {
+                    skipped = 0;
+                }
+
+                let result = match self.assembler.push(byte) {
+                    Some(Ok((cmd, payload))) => self.dispatch(cmd, payload),
+                    Some(Err(err)) => Err(err),
+                    None => Ok(()),
+                };
+
+                if let Err(err) = result {
+                    error = error.or(Some(err));
+                }
+            }
+
+            if let Some(err) = error {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Read raw data received while the module is in transparent mode.
+    ///
+    /// Returns the number of bytes read into `buf`.
+    pub async fn read_transparent(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<usize, IngestError<S::Error>> {
+        match self.serial.read(buf).await {
+            Ok(0) if !buf.is_empty() => Err(IngestError::Closed),
+            Ok(len) => Ok(len),
+            Err(err) if is_closed_kind(err.kind()) => Err(IngestError::Closed),
+            Err(err) => Err(IngestError::Io(err)),
+        }
+    }
+
+    /// Wait until the module is back in command mode, ready to [ingest](Ingress::ingest) again.
+    ///
+    /// Returns straight away unless the paired radio has switched the module to transparent mode,
+    /// see [`IngestError::Transparent`].
+    pub async fn wait_for_command_mode(&mut self) {
+        let Some(state) = self.guard.0 else {
+            return;
+        };
+
+        poll_fn(|cx| {
+            if !state.transparent() {
+                return Poll::Ready(());
+            }
+
+            state.ingress_waker.register(cx.waker());
+            // the mode may have changed before the waker was registered.
+            if state.transparent() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
+    /// Receive frames until a [fatal](IngestError::is_fatal) error occurs, returning it.
+    ///
+    /// Recoverable errors are skipped, and while the module is in transparent mode this waits
+    /// for it to return to command mode, leaving its data unread. Use [`Ingress::ingest`]
+    /// instead to handle each error, or to read data with [`Ingress::read_transparent`].
+    ///
+    /// Frames are received with [`Ingress::ingest_buffered`], so the future can be dropped and
+    /// run again without losing a partially received frame.
+    pub async fn run(&mut self) -> IngestError<S::Error> {
+        loop {
+            match self.ingest_buffered().await {
+                Err(IngestError::Transparent) => self.wait_for_command_mode().await,
+                Err(err) if err.is_fatal() => return err,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns true if the paired radio has switched the module to transparent mode.
+    fn is_transparent(&self) -> bool {
+        self.guard.0.is_some_and(SharedState::transparent)
+    }
+
+    /// Queue a received frame for the radio.
+    fn dispatch(&mut self, cmd: u8, mut payload: Buffer) -> Result<(), IngestError<S::Error>> {
+        if let Some(event) = Event::try_from_raw(cmd) {
+            trace!("rx {:?}: {}", event, Hex(&payload));
+            let rssi = match event {
+                Event::DataReceived | Event::DataRepeat if self.split_rssi => {
+                    payload.pop().map(|rssi| rssi as i8)
+                }
+                _ => None,
+            };
+            let frame = Frame::<Event> {
+                command: event,
+                data: payload,
+                rssi,
+            };
+            if self.event.enqueue(frame).is_err() {
+                self.record_drop();
+            }
+            self.wake_radio();
+            return Ok(());
+        }
+
+        if let Some(response) = Response::try_from_raw(cmd) {
+            trace!("rx {:?}: {}", response, Hex(&payload));
+            if self
+                .response
+                .enqueue(Frame::from_buffer(response, payload))
+                .is_err()
+            {
+                self.record_drop();
+            }
+            self.wake_radio();
+            return Ok(());
+        }
+
+        trace!("rx unknown {:#04x}: {}", cmd, Hex(&payload));
+        Err(IngestError::Decode(DecodeError::UnknownCommand(cmd)))
+    }
+
+    /// Wake the radio if it's waiting for a frame.
+    fn wake_radio(&self) {
+        if let Some(state) = self.guard.0 {
+            state.radio_waker.wake();
+        }
+    }
+
+    /// Count a frame dropped because its queue was full.
+    fn record_drop(&mut self) {
+        self.dropped += 1;
+        if let Some(state) = self.guard.0 {
+            state.set_dropped_frames(self.dropped);
+        }
+    }
+
+    /// Read the remainder of a frame, giving up once the frame timeout has elapsed.
+    ///
+    /// Returns `false` if the timeout elapsed before the buffer was filled.
+    async fn read_frame(&mut self, buf: &mut [u8]) -> Result<bool, IngestError<S::Error>> {
+        let read = self.serial.read_exact(buf);
+
+        let result = match self.timeout {
+            Some(timeout) => match with_timeout(&mut self.timer, timeout, read).await {
+                Ok(result) => result,
+                Err(Timeout) => return Ok(false),
+            },
+            None => read.await,
+        };
+
+        result.map(|()| true).map_err(read_error)
+    }
+}
+
+/// Convert a failed exact read, distinguishing a closed serial stream from other errors.
+fn read_error<E: embedded_io_async::Error>(err: ReadExactError<E>) -> IngestError<E> {
+    match err {
+        ReadExactError::UnexpectedEof => IngestError::Closed,
+        ReadExactError::Other(err) if is_closed_kind(err.kind()) => IngestError::Closed,
+        ReadExactError::Other(err) => IngestError::Io(err),
+    }
+}
+
+/// Returns true if the error kind indicates the serial stream has been closed.
+fn is_closed_kind(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::BrokenPipe
+            | ErrorKind::NotConnected
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
+/// Ingest error.
+#[derive(Debug, Clone, Copy)]
+pub enum IngestError<IO> {
+    /// No start byte was found within the limit set by [`Ingress::with_resync_limit`].
+    StartByte,
+    /// Frame couldn't be decoded, because its length, checksum or command id is invalid.
+    ///
+    /// The frame is discarded, ingest may be resumed by calling [`Ingress::ingest`] again.
+    Decode(DecodeError),
+    /// Module is in transparent mode, so received bytes are data rather than frames.
+    ///
+    /// Only reported by an ingress created with [`Radio::from_storage`]. It's reported on every
+    /// call until the module is back in command mode, so rather than retrying straight away read
+    /// the data with [`Ingress::read_transparent`], or wait with
+    /// [`Ingress::wait_for_command_mode`].
+    Transparent,
+    /// Serial stream has been closed.
+    Closed,
+    /// Reading the serial stream failed.
+    Io(IO),
+}
+
+impl<IO> IngestError<IO> {
+    /// Returns true if ingest can't continue, because the serial stream is closed or failing.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Closed | Self::Io(_))
+    }
+}
+
+impl<IO> fmt::Display for IngestError<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartByte => f.write_str("no start byte found"),
+            Self::Decode(err) => write!(f, "invalid frame: {}", err),
+            Self::Transparent => f.write_str("module is in transparent mode"),
+            Self::Closed => f.write_str("serial stream closed"),
+            Self::Io(_) => f.write_str("serial port error"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<IO: std::error::Error + 'static> std::error::Error for IngestError<IO> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(err) => Some(err),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Waker};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct MockSerial;
+
+    impl embedded_io_async::ErrorType for MockSerial {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for MockSerial {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(buf.len())
+        }
+    }
+
+    impl Read for MockSerial {
+        async fn read(&mut self, _buf: &mut [u8]) -> Result<usize, Self::Error> {
+            core::future::pending().await
+        }
+    }
+
+    /// Writer which never completes a write.
+    struct StuckSerial;
+
+    impl embedded_io_async::ErrorType for StuckSerial {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for StuckSerial {
+        async fn write(&mut self, _buf: &[u8]) -> Result<usize, Self::Error> {
+            core::future::pending().await
+        }
+    }
+
+    /// Writer which stays pending for a number of polls before accepting writes.
+    struct SlowSerial(usize);
+
+    impl embedded_io_async::ErrorType for SlowSerial {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for SlowSerial {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            poll_fn(|cx| {
+                if self.0 == 0 {
+                    return Poll::Ready(Ok(buf.len()));
+                }
+
+                self.0 -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await
+        }
+    }
+
+    /// Reader which returns each chunk in turn, failing where a chunk is [`None`].
+    ///
+    /// A chunk longer than the read buffer is returned over several reads.
+    struct ScriptedSerial {
+        script: &'static [Option<&'static [u8]>],
+        offset: usize,
+    }
+
+    impl ScriptedSerial {
+        fn new(script: &'static [Option<&'static [u8]>]) -> Self {
+            Self { script, offset: 0 }
+        }
+    }
+
+    impl embedded_io_async::ErrorType for ScriptedSerial {
+        type Error = ErrorKind;
+    }
+
+    impl Read for ScriptedSerial {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let Some((chunk, rest)) = self.script.split_first() else {
+                return Ok(0);
+            };
+
+            let Some(chunk) = chunk else {
+                self.script = rest;
+                return Err(ErrorKind::Other);
+            };
+
+            let remaining = &chunk[self.offset..];
+            let len = buf.len().min(remaining.len());
+            buf[..len].copy_from_slice(&remaining[..len]);
+
+            self.offset += len;
+            if self.offset == chunk.len() {
+                self.script = rest;
+                self.offset = 0;
+            }
+
+            Ok(len)
+        }
+    }
+
+    /// Simulated module, answering each request written to it.
+    ///
+    /// User settings are stored and read back, other requests are answered with the next scripted
+    /// reply, or a success status once the script runs out. Clones share the same module, so one
+    /// can be the writer and another the reader.
+    #[derive(Clone, Default)]
+    struct MockModule(Rc<RefCell<ModuleState>>);
+
+    #[derive(Default)]
+    struct ModuleState {
+        tx: std::vec::Vec<u8>,
+        rx: std::collections::VecDeque<u8>,
+        replies: std::collections::VecDeque<&'static [u8]>,
+        settings: std::collections::BTreeMap<u8, std::vec::Vec<u8>>,
+        requests: std::vec::Vec<(u8, std::vec::Vec<u8>)>,
+    }
+
+    impl MockModule {
+        fn with_replies(replies: &[&'static [u8]]) -> Self {
+            let module = Self::default();
+            module.0.borrow_mut().replies.extend(replies);
+            module
+        }
+
+        /// Queue a frame sent by the module unprompted, such as an event.
+        fn push_frame(&self, cmd: u8, data: &[u8]) {
+            let mut raw = [0; MAX_FRAME_LEN];
+            let len = command(&mut raw, cmd, data);
+            self.0.borrow_mut().rx.extend(&raw[..len]);
+        }
+
+        /// Number of bytes sent by the module which haven't been read yet.
+        fn unread(&self) -> usize {
+            self.0.borrow().rx.len()
+        }
+
+        /// Command codes of the requests received so far.
+        fn requests(&self) -> std::vec::Vec<u8> {
+            self.0
+                .borrow()
+                .requests
+                .iter()
+                .map(|(cmd, _)| *cmd)
+                .collect()
+        }
+
+        /// Data of the most recent request.
+        fn last_request(&self) -> std::vec::Vec<u8> {
+            let state = self.0.borrow();
+            state.requests.last().map(|(_, data)| data.clone()).unwrap()
+        }
+
+        fn setting(&self, setting: Setting) -> Option<std::vec::Vec<u8>> {
+            self.0.borrow().settings.get(&(setting as u8)).cloned()
+        }
+    }
+
+    impl ModuleState {
+        fn reply(&mut self, cmd: u8, data: &[u8]) {
+            let mut reply = std::vec::Vec::from([0x00]);
+            match (cmd, data) {
+                (0x09, [setting, value @ ..]) => {
+                    self.settings.insert(*setting, value.to_vec());
+                }
+                (0x0A, [setting]) => match self.settings.get(setting) {
+                    Some(value) => reply.extend_from_slice(value),
+                    None => reply = std::vec::Vec::from([0x01]),
+                },
+                _ => {
+                    if let Some(scripted) = self.replies.pop_front() {
+                        reply = scripted.to_vec();
+                    }
+                }
+            }
+
+            // both sends are confirmed by the same response.
+            let response = if cmd == 0x01 { 0x40 } else { cmd | 0x40 };
+            let mut raw = [0; MAX_FRAME_LEN];
+            let len = command(&mut raw, response, &reply);
+            self.rx.extend(&raw[..len]);
+            self.requests.push((cmd, data.to_vec()));
+        }
+    }
+
+    impl embedded_io_async::ErrorType for MockModule {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for MockModule {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            let mut state = self.0.borrow_mut();
+            state.tx.extend_from_slice(buf);
+
+            // answer each complete frame, made up of the start byte, command, length, data and
+            // checksum.
+            while let [START, cmd, len, ..] = *state.tx {
+                let end = 3 + len as usize + 1;
+                if state.tx.len() < end {
+                    break;
+                }
+
+                let frame: std::vec::Vec<u8> = state.tx.drain(..end).collect();
+                state.reply(cmd, &frame[3..end - 1]);
+            }
+
+            Ok(buf.len())
+        }
+    }
+
+    impl Read for MockModule {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            poll_fn(|cx| {
+                let mut state = self.0.borrow_mut();
+                if state.rx.is_empty() {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                let len = buf.len().min(state.rx.len());
+                for (byte, rx) in buf.iter_mut().zip(state.rx.drain(..len)) {
+                    *byte = rx;
+                }
+                Poll::Ready(Ok(len))
+            })
+            .await
+        }
+    }
+
+    /// Timer advancing a shared clock by a millisecond each time a delay is polled.
+    ///
+    /// Delays which run to completion are recorded, those abandoned by a timeout aren't.
+    #[derive(Clone, Default)]
+    struct MockTimer(Rc<RefCell<TimerState>>);
+
+    #[derive(Default)]
+    struct TimerState {
+        now: Duration,
+        elapsed: std::vec::Vec<Duration>,
+    }
+
+    impl MockTimer {
+        fn elapsed(&self) -> std::vec::Vec<Duration> {
+            self.0.borrow().elapsed.clone()
+        }
+    }
+
+    impl Clock for MockTimer {
+        fn now(&self) -> Duration {
+            self.0.borrow().now
+        }
+    }
+
+    impl Timer for MockTimer {
+        async fn delay(&mut self, duration: Duration) {
+            let end = self.now() + duration;
+            poll_fn(|cx| {
+                let mut state = self.0.borrow_mut();
+                if state.now >= end {
+                    return Poll::Ready(());
+                }
+
+                state.now += Duration::from_millis(1);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+
+            self.0.borrow_mut().elapsed.push(duration);
+        }
+    }
+
+    fn poll_once<F: Future>(future: F) -> Poll<F::Output> {
+        let mut cx = Context::from_waker(Waker::noop());
+        pin!(future).poll(&mut cx)
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = pin!(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    /// Block on a future while ingesting in the background, as a receive task would.
+    fn block_on_with<R: Read, T: MaybeTimer, F: Future>(
+        ingress: &mut Ingress<'_, R, T>,
+        future: F,
+    ) -> F::Output {
+        block_on(transceiver::drive(future, ingress.run())).unwrap()
+    }
+
+    #[test]
+    fn poll_event_cancellation() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(poll_once(radio.poll_event()).is_pending());
+
+        ingress
+            .event
+            .enqueue(Frame::new(Event::DataReceived, Payload::new()))
+            .unwrap();
+        assert_eq!(ingress.event_len(), 1);
+        assert_eq!(radio.event_len(), 1);
+
+        match poll_once(radio.poll_event()) {
+            Poll::Ready(frame) => assert!(matches!(frame.command(), Event::DataReceived)),
+            Poll::Pending => panic!("event was lost"),
+        }
+        assert!(radio.try_poll_event().is_none());
+    }
+
+    #[test]
+    fn poll_events_batch() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        for event in [Event::DataReceived, Event::PacketTransmit, Event::Wakeup] {
+            ingress
+                .event
+                .enqueue(Frame::new(event, Payload::new()))
+                .unwrap();
+        }
+
+        let mut buf: [_; 2] = core::array::from_fn(|_| Frame::new(Event::Reset, Payload::new()));
+        assert_eq!(block_on(radio.poll_events_into(&mut buf)), 2);
+        assert_eq!(*buf[0].command(), Event::DataReceived);
+        assert_eq!(*buf[1].command(), Event::PacketTransmit);
+
+        assert_eq!(block_on(radio.poll_events_into(&mut buf)), 1);
+        assert_eq!(*buf[0].command(), Event::Wakeup);
+        assert!(poll_once(radio.poll_events_into(&mut buf)).is_pending());
+    }
+
+    #[test]
+    fn ingest_closed() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (_radio, mut ingress) = Radio::new(
+            MockSerial,
+            [START, 0x81].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Closed)
+        ));
+    }
+
+    #[test]
+    fn standby_wakeup() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Standby, payload(&[0x00])))
+            .unwrap();
+        block_on(radio.standby()).unwrap();
+        assert!(!radio.is_awake());
+        assert!(matches!(
+            block_on(radio.rssi()),
+            Err(CommandError::NotAwake)
+        ));
+
+        ingress
+            .event
+            .enqueue(Frame::new(Event::Wakeup, Payload::new()))
+            .unwrap();
+        block_on(radio.wait_for_wakeup());
+        assert!(radio.is_awake());
+    }
+
+    #[test]
+    fn shutdown_gracefully() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        // the radio is consumed, handing back the serial writer once the module acknowledged.
+        let serial = block_on_with(&mut ingress, radio.shutdown_gracefully()).unwrap();
+        assert_eq!(serial.requests(), [Request::Shutdown as u8]);
+        assert_eq!(module.unread(), 0);
+    }
+
+    #[test]
+    fn shutdown_gracefully_rejected() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x01]]);
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        assert!(matches!(
+            block_on_with(&mut ingress, radio.shutdown_gracefully()),
+            Err(CommandError::Status(0x01))
+        ));
+    }
+
+    #[test]
+    fn cancelled_transaction() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(!radio.is_awaiting_response());
+        assert!(poll_once(radio.reset()).is_pending());
+        assert!(radio.is_awaiting_response());
+        assert!(matches!(block_on(radio.rssi()), Err(CommandError::Busy)));
+
+        radio.clear_transaction();
+        assert!(poll_once(radio.rssi()).is_pending());
+    }
+
+    #[test]
+    fn orphaned_response_discarded() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(poll_once(radio.reset()).is_pending());
+        radio.clear_transaction();
+
+        let mut rssi = pin!(radio.rssi());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(rssi.as_mut().poll(&mut cx).is_pending());
+
+        // the reset confirmation arrives late, after the rssi request was written.
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Reset, payload(&[0x00])))
+            .unwrap();
+        assert!(rssi.as_mut().poll(&mut cx).is_pending());
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+        assert!(matches!(rssi.as_mut().poll(&mut cx), Poll::Ready(Ok(0xC4))));
+    }
+
+    #[test]
+    fn send_duty_cycle() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let duty_cycle = DutyCycle::new(
+            RfProfile::Rate1k2,
+            Duration::from_millis(30),
+            Duration::from_secs(1),
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio.with_timer(timer.clone()).with_duty_cycle(duty_cycle);
+
+        assert!(poll_once(radio.send(b"abc")).is_pending());
+        radio.clear_transaction();
+        assert!(matches!(
+            block_on(radio.send(b"abc")),
+            Err(CommandError::DutyCycleExceeded)
+        ));
+        assert_eq!(radio.duty_cycle_used(), Some(Duration::from_millis(20)));
+
+        // the budget is restored once the window has passed on the timer.
+        block_on(timer.clone().delay(Duration::from_secs(1)));
+        assert!(poll_once(radio.send(b"abc")).is_pending());
+        assert_eq!(radio.duty_cycle_used(), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn send_duty_cycle_unwritten() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let duty_cycle = DutyCycle::new(
+            RfProfile::Rate1k2,
+            Duration::from_millis(30),
+            Duration::from_secs(1),
+        );
+        let mut radio = radio
+            .with_timer(MockTimer::default())
+            .with_duty_cycle(duty_cycle);
+
+        radio.awake = false;
+        assert!(matches!(
+            block_on(radio.send(b"abc")),
+            Err(CommandError::NotAwake)
+        ));
+        assert_eq!(radio.duty_cycle_used(), Some(Duration::ZERO));
+
+        radio.awake = true;
+        radio.in_flight = true;
+        assert!(matches!(
+            block_on(radio.send(b"abc")),
+            Err(CommandError::Busy)
+        ));
+        assert_eq!(radio.duty_cycle_used(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn send_busy_retry() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let mut radio = radio
+            .with_timer(MockTimer::default())
+            .with_busy_retry(1, Duration::from_millis(1));
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::SendData, payload(&[0x04])))
+            .unwrap();
+
+        // the busy response is retried, so the send is waiting on a second response.
+        assert!(poll_once(radio.send(b"hello")).is_pending());
+        assert_eq!(radio.response_len(), 0);
+    }
+
+    #[test]
+    fn ingress_dropped() {
+        let mut storage = RadioStorage::new();
+        let (mut radio, ingress) = Radio::from_storage(MockSerial, MockSerial, &mut storage);
+
+        drop(ingress);
+        assert!(matches!(
+            block_on(radio.rssi()),
+            Err(CommandError::IngressGone)
+        ));
+    }
+
+    #[test]
+    fn ingress_dropped_after_response() {
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) = Radio::from_storage(MockSerial, MockSerial, &mut storage);
+
+        let mut rssi = pin!(radio.rssi());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(rssi.as_mut().poll(&mut cx).is_pending());
+
+        // the response queued by the ingress on its way out is still received.
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+        drop(ingress);
+        assert!(matches!(rssi.as_mut().poll(&mut cx), Poll::Ready(Ok(0xC4))));
+    }
+
+    #[test]
+    fn ingest_buffered_frame() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            [0x00, START, 0x81, 0x01, 0x48, 0xCA].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(radio.event_len(), 1);
+        assert_eq!(block_on(radio.poll_event()).data(), &[0x48]);
+    }
+
+    #[test]
+    fn ingest_leading_garbage() {
+        for raw in [
+            &[0x00, START, 0x81, 0x01, 0x48, 0xCA][..],
+            &[0x00, 0x00, START, 0x81, 0x01, 0x48, 0xCA][..],
+        ] {
+            let mut response_queue = Queue::new();
+            let mut event_queue = Queue::new();
+            let (mut radio, mut ingress) =
+                Radio::new(MockSerial, raw, &mut response_queue, &mut event_queue);
+
+            assert!(matches!(
+                block_on(ingress.ingest()),
+                Err(IngestError::Closed)
+            ));
+            assert_eq!(radio.try_poll_event().unwrap().data(), &[0x48]);
+        }
+    }
+
+    #[test]
+    fn ingest_resync_limit() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (_radio, ingress) = Radio::new(
+            MockSerial,
+            [0x00; 9].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let mut ingress = ingress.with_resync_limit(4);
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::StartByte)
+        ));
+    }
+
+    #[test]
+    fn ingest_resync_limit_exact() {
+        let frame = [START, 0x81, 0x01, 0x48, 0xCA];
+
+        // each skipped byte counts once, so exactly `limit` bytes may precede a frame.
+        for (skipped, received) in [(4, true), (5, false)] {
+            let mut raw = std::vec![0x00; skipped];
+            raw.extend_from_slice(&frame);
+
+            let mut response_queue = Queue::new();
+            let mut event_queue = Queue::new();
+            let (mut radio, ingress) = Radio::new(
+                MockSerial,
+                raw.as_slice(),
+                &mut response_queue,
+                &mut event_queue,
+            );
+            let mut ingress = ingress.with_resync_limit(4);
+
+            let result = block_on(ingress.ingest());
+            if received {
+                assert!(matches!(result, Err(IngestError::Closed)));
+                assert_eq!(radio.try_poll_event().unwrap().data(), &[0x48]);
+            } else {
+                assert!(matches!(result, Err(IngestError::StartByte)));
+                assert!(radio.try_poll_event().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn ingest_max_payload_len() {
+        let mut raw = [0; MAX_FRAME_LEN];
+        command(&mut raw, 0x81, &[0xAB; MAX_PAYLOAD_LEN]);
+
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            raw.as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Closed)
+        ));
+        let event = radio.try_poll_event().unwrap();
+        assert_eq!(*event.command(), Event::DataReceived);
+        assert_eq!(event.data(), &[0xAB; MAX_PAYLOAD_LEN]);
+    }
+
+    #[test]
+    fn ingest_back_to_back_frames() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            [START, 0x81, 0x01, 0x48, 0xCA, START, 0x8F, 0x00, 0x8D].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(radio.try_poll_event().unwrap().data(), &[0x48]);
+        assert_eq!(*radio.try_poll_event().unwrap().command(), Event::Wakeup);
+    }
+
+    #[test]
+    fn ingest_partial_payload() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            ScriptedSerial::new(&[Some(&[START, 0x81, 0x02]), Some(&[0x48]), None]),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Io(ErrorKind::Other))
+        ));
+        assert!(radio.try_poll_event().is_none());
+    }
+
+    #[test]
+    fn ingest_header_error() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            ScriptedSerial::new(&[Some(&[START]), None]),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Io(ErrorKind::Other))
+        ));
+        assert!(radio.try_poll_event().is_none());
+    }
+
+    #[test]
+    fn ingest_buffered_error() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            ScriptedSerial::new(&[Some(&[START, 0x8F, 0x00, 0x8D]), None]),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        // frames completed before the error are still queued.
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Io(ErrorKind::Other))
+        ));
+        assert_eq!(*radio.try_poll_event().unwrap().command(), Event::Wakeup);
+    }
+
+    #[test]
+    fn read_transparent_error() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (_radio, mut ingress) = Radio::new(
+            MockSerial,
+            ScriptedSerial::new(&[Some(&[0x48]), None]),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        let mut buf = [0; 4];
+        assert!(matches!(
+            block_on(ingress.read_transparent(&mut buf)),
+            Ok(1)
+        ));
+        assert_eq!(buf[0], 0x48);
+        assert!(matches!(
+            block_on(ingress.read_transparent(&mut buf)),
+            Err(IngestError::Io(ErrorKind::Other))
+        ));
+    }
+
+    #[test]
+    fn ingest_bad_checksum() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            [START, 0x81, 0x01, 0x48, 0x00, START, 0x81, 0x00, 0x83].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Decode(DecodeError::Checksum))
+        ));
+        assert!(radio.try_poll_event().is_none());
+
+        // only the single checksum byte is consumed, so the next frame is intact.
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(radio.try_poll_event().unwrap().data(), &[]);
+    }
+
+    #[test]
+    fn ingest_oversized_payload_len() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (_radio, mut ingress) = Radio::new(
+            MockSerial,
+            [START, 0x81, MAX_PAYLOAD_LEN as u8 + 1].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Decode(DecodeError::PayloadLength))
+        ));
+    }
+
+    #[test]
+    fn tap_mirrors_bytes() {
+        let raw = [START, 0x81, 0x02, 0x48, 0xC4, 0x0D];
+        let mut read = std::vec::Vec::new();
+        let mut written = std::vec::Vec::new();
+
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            Tap::new(MockSerial, |bytes: &[u8]| written.extend_from_slice(bytes)),
+            Tap::new(raw.as_slice(), |bytes: &[u8]| read.extend_from_slice(bytes)),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(
+            *radio.try_poll_event().unwrap().command(),
+            Event::DataReceived
+        );
+
+        // no response is queued, so the command waits once the request is written.
+        assert!(poll_once(radio.rssi()).is_pending());
+        drop((radio, ingress));
+
+        assert_eq!(read, raw);
+        assert_eq!(written, [START, 0x0D, 0x00, 0x0F]);
+    }
+
+    #[test]
+    fn small_event_queue() {
+        let raw = [START, 0x8F, 0x00, 0x8D, START, 0x85, 0x00, 0x87];
+        let mut storage = RadioStorage::<2>::with_event_queue();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(MockSerial, raw.as_slice(), &mut storage);
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(*radio.try_poll_event().unwrap().command(), Event::Wakeup);
+        assert!(radio.try_poll_event().is_none());
+        assert_eq!(ingress.dropped_frames(), 1);
+        assert_eq!(radio.dropped_frames(), Some(1));
+    }
+
+    #[test]
+    fn drop_oldest_event() {
+        let raw = [
+            START, 0x8F, 0x00, 0x8D, START, 0x85, 0x00, 0x87, START, 0x81, 0x00, 0x83,
+        ];
+        let mut storage =
+            RadioStorage::<3>::with_event_queue().with_overflow_policy(OverflowPolicy::DropOldest);
+        let (mut radio, mut ingress) =
+            Radio::from_storage(MockSerial, raw.as_slice(), &mut storage);
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(ingress.event_len(), 2);
+        assert_eq!(*radio.try_poll_event().unwrap().command(), Event::Reset);
+        assert_eq!(
+            *radio.try_poll_event().unwrap().command(),
+            Event::DataReceived
+        );
+        assert!(radio.try_poll_event().is_none());
+        assert_eq!(radio.dropped_frames(), Some(1));
+    }
+
+    #[test]
+    fn drop_oldest_contended() {
+        use storage::Side;
+
+        let mut storage =
+            RadioStorage::<3>::with_event_queue().with_overflow_policy(OverflowPolicy::DropOldest);
+        let (mut radio, mut ingress) = Radio::from_storage(MockSerial, [].as_slice(), &mut storage);
+        let state = radio.state.unwrap();
+
+        let frame = Frame::new(Event::Wakeup, payload(&[]));
+        let rejected = state.lock_events(Side::Radio, || ingress.event.enqueue(frame).is_err());
+        assert_eq!(rejected, Some(true));
+        assert_eq!(
+            state.lock_events(Side::Ingress, || radio.event.dequeue().is_none()),
+            Some(true)
+        );
+
+        ingress
+            .event
+            .enqueue(Frame::new(Event::Wakeup, payload(&[])))
+            .unwrap();
+        assert_eq!(*radio.try_poll_event().unwrap().command(), Event::Wakeup);
+    }
+
+    #[test]
+    fn drain_events() {
+        let raw = [START, 0x8F, 0x00, 0x8D, START, 0x85, 0x00, 0x87];
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(MockSerial, raw.as_slice(), &mut storage);
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(radio.drain_events(), 2);
+        assert!(radio.try_poll_event().is_none());
+        assert_eq!(radio.drain_events(), 0);
+    }
+
+    #[test]
+    fn ingest_rssi_split() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, ingress) = Radio::new(
+            MockSerial,
+            [START, 0x81, 0x02, 0x48, 0xC4, 0x0D, START, 0x81, 0x00, 0x83].as_slice(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let mut ingress = ingress.with_rssi_split();
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+
+        let event = radio.try_poll_event().unwrap();
+        assert_eq!(event.data(), &[0x48]);
+        assert_eq!(event.rssi(), Some(-60));
+        assert_eq!(event.received(AddressMode::None).unwrap().data, &[0x48]);
+
+        let (command, data, rssi) = event.into_parts();
+        assert_eq!(command, Event::DataReceived);
+        assert_eq!(data, [0x48]);
+        assert_eq!(rssi, Some(-60));
+
+        let empty = radio.try_poll_event().unwrap();
+        assert_eq!(empty.data(), &[]);
+        assert_eq!(empty.rssi(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn frame_vec_parts() {
+        let frame = Frame::from_vec(Event::DataReceived, std::vec![0x48, 0x69]);
+        assert_eq!(frame.data(), &[0x48, 0x69]);
+
+        let (command, data, rssi) = frame.into_vec_parts();
+        assert_eq!(command, Event::DataReceived);
+        assert_eq!(data, [0x48, 0x69]);
+        assert_eq!(rssi, None);
+
+        let (_, data, _) = Frame::new(Event::DataReceived, payload(&[0x48])).into_parts();
+        assert_eq!(data, [0x48]);
+    }
+
+    #[test]
+    fn transparent_mode() {
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) = Radio::from_storage(MockSerial, MockSerial, &mut storage);
+
+        radio.assume_mode(Mode::Transparent);
+        assert!(matches!(
+            block_on(ingress.ingest()),
+            Err(IngestError::Transparent)
+        ));
+
+        radio.assume_mode(Mode::Command);
+        assert!(poll_once(ingress.ingest()).is_pending());
+    }
+
+    /// Waker counting the number of times it's woken.
+    struct CountingWaker(std::sync::atomic::AtomicUsize);
+
+    impl CountingWaker {
+        fn count(&self) -> usize {
+            self.0.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    impl std::task::Wake for CountingWaker {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn poll_event_woken() {
+        let raw = [START, 0x8F, 0x00, 0x8D];
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(MockSerial, raw.as_slice(), &mut storage);
+        let wakes = std::sync::Arc::new(CountingWaker(Default::default()));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut event = pin!(radio.poll_event());
+        assert!(event.as_mut().poll(&mut cx).is_pending());
+        // waiting for the ingress rather than polling again straight away.
+        assert_eq!(wakes.count(), 0);
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert_eq!(wakes.count(), 1);
+        assert!(matches!(
+            event.as_mut().poll(&mut cx),
+            Poll::Ready(event) if *event.command() == Event::Wakeup
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "futures")]
+    fn event_stream() {
+        use core::pin::Pin;
+        use futures_core::Stream;
+
+        let raw = [START, 0x8F, 0x00, 0x8D, START, 0x81, 0x02, 0x48, 0xC4, 0x0D];
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(MockSerial, raw.as_slice(), &mut storage);
+        let wakes = std::sync::Arc::new(CountingWaker(Default::default()));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut events = radio.poll_events();
+        assert!(Pin::new(&mut events).poll_next(&mut cx).is_pending());
+        assert_eq!(wakes.count(), 0);
+
+        assert!(matches!(
+            block_on(ingress.ingest_buffered()),
+            Err(IngestError::Closed)
+        ));
+        assert!(wakes.count() > 0);
+
+        // events are yielded in order, then the stream waits for more rather than ending.
+        assert!(matches!(
+            Pin::new(&mut events).poll_next(&mut cx),
+            Poll::Ready(Some(event)) if *event.command() == Event::Wakeup
+        ));
+        assert!(matches!(
+            Pin::new(&mut events).poll_next(&mut cx),
+            Poll::Ready(Some(event)) if *event.command() == Event::DataReceived
+        ));
+        assert!(Pin::new(&mut events).poll_next(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn wait_for_command_mode() {
+        let mut storage = RadioStorage::new();
+        let (mut radio, mut ingress) = Radio::from_storage(MockSerial, MockSerial, &mut storage);
+        let wakes = std::sync::Arc::new(CountingWaker(Default::default()));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        radio.assume_mode(Mode::Transparent);
+        let mut run = pin!(ingress.run());
+        assert!(run.as_mut().poll(&mut cx).is_pending());
+        assert!(run.as_mut().poll(&mut cx).is_pending());
+        // waiting for command mode rather than retrying straight away.
+        assert_eq!(wakes.count(), 0);
+
+        radio.assume_mode(Mode::Command);
+        assert_eq!(wakes.count(), 1);
+        assert!(run.as_mut().poll(&mut cx).is_pending());
+    }
+
+    #[test]
+    fn transceiver_interleaved() {
+        let module = MockModule::default();
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let mut transceiver = Transceiver::new(
+            module.clone(),
+            module.clone(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        // an event arrives ahead of the response to the send.
+        module.push_frame(0x81, &[0x48, 0xC4]);
+        assert!(block_on(transceiver.send(b"hi")).is_ok());
+
+        let event = block_on(transceiver.poll_event()).unwrap();
+        assert_eq!(*event.command(), Event::DataReceived);
+        assert_eq!(event.data(), &[0x48, 0xC4]);
+    }
+
+    #[test]
+    fn transceiver_partial_frame() {
+        let module = MockModule::default();
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let mut transceiver = Transceiver::new(
+            module.clone(),
+            module.clone(),
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        // the first half of the event is read while an unrelated operation runs.
+        module.0.borrow_mut().rx.extend([START, 0x81, 0x02, 0x48]);
+        let mut yielded = false;
+        block_on(transceiver.run(async |_| {
+            poll_fn(|cx| {
+                if yielded {
+                    return Poll::Ready(());
+                }
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await
+        }))
+        .unwrap();
+        assert_eq!(module.unread(), 0);
+
+        module.0.borrow_mut().rx.extend([0xC4, 0x0D]);
+        let event = block_on(transceiver.poll_event()).unwrap();
+        assert_eq!(*event.command(), Event::DataReceived);
+        assert_eq!(event.data(), &[0x48, 0xC4]);
+    }
+
+    #[test]
+    fn transceiver_fatal_error() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, ingress) = Radio::new(
+            MockSerial,
+            ScriptedSerial::new(&[None, Some(&[START, 0x40, 0x01, 0x00, 0x43])]),
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let radio = radio.with_timer(timer.clone());
+        let ingress = ingress.with_timeout(timer.clone(), Duration::from_millis(10));
+        let mut transceiver = Transceiver::from_parts(radio, ingress);
+
+        // the read error stops the receive path, abandoning the send waiting for its response.
+        assert!(matches!(
+            block_on(transceiver.send(b"hi")),
+            Err(CommandError::IngressGone)
+        ));
+        assert!(matches!(
+            transceiver.ingest_error(),
+            Some(IngestError::Io(ErrorKind::Other))
+        ));
+        assert!(timer.now() < Request::SendData.default_timeout());
+
+        // later operations fail straight away rather than waiting on the stopped receive path.
+        assert!(matches!(
+            block_on(transceiver.poll_event()),
+            Err(CommandError::IngressGone)
+        ));
+    }
+
+    #[test]
+    fn transceiver_transparent_mode() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        let (radio, ingress) = Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let mut transceiver = Transceiver::from_parts(radio, ingress);
+
+        block_on(transceiver.run(async |radio| radio.mode(Mode::Transparent).await))
+            .unwrap()
+            .unwrap();
+        module.push_frame(0x81, &[0x48, 0xC4]);
+
+        // while transparent the receive path leaves the serial data alone.
+        {
+            let mut cx = Context::from_waker(Waker::noop());
+            let mut event = pin!(transceiver.poll_event());
+            for _ in 0..3 {
+                assert!(event.as_mut().poll(&mut cx).is_pending());
+            }
+        }
+        assert_eq!(module.unread(), 6);
+
+        block_on(transceiver.run(async |radio| radio.assume_mode(Mode::Command))).unwrap();
+        let event = block_on(transceiver.poll_event()).unwrap();
+        assert_eq!(*event.command(), Event::DataReceived);
+        assert_eq!(module.unread(), 0);
+    }
+
+    #[test]
+    fn response_timeouts() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio
+            .with_timer(timer.clone())
+            .with_response_timeout(Request::Rssi, Duration::from_millis(30));
+
+        assert_eq!(
+            radio.response_timeout(Request::Rssi),
+            Duration::from_millis(30)
+        );
+        assert_eq!(
+            radio.response_timeout(Request::SendData),
+            Request::SendData.default_timeout()
+        );
+
+        // the override applies only to its own request, others keep their default.
+        assert!(matches!(block_on(radio.rssi()), Err(CommandError::Timeout)));
+        assert_eq!(timer.now(), Duration::from_millis(30));
+        assert!(matches!(
+            block_on(radio.get_user_setting(Setting::ConfigFlags)),
+            Err(CommandError::Timeout)
+        ));
+        assert_eq!(
+            timer.now(),
+            Duration::from_millis(30) + Request::GetUserSetting.default_timeout()
+        );
+    }
+
+    #[test]
+    fn write_timeout() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            StuckSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio
+            .with_timer(timer.clone())
+            .with_write_timeout(Duration::from_millis(10));
+
+        assert!(matches!(block_on(radio.rssi()), Err(CommandError::Timeout)));
+        assert!(!radio.in_flight);
+        assert_eq!(timer.now(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn hard_reset() {
+        struct MockPin {
+            timer: MockTimer,
+            levels: std::vec::Vec<(bool, Duration)>,
+        }
+
+        impl ResetPin for MockPin {
+            type Error = core::convert::Infallible;
+
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                self.levels.push((false, self.timer.now()));
+                Ok(())
+            }
+
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                self.levels.push((true, self.timer.now()));
+                Ok(())
+            }
+        }
+
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio.with_timer(timer.clone());
+        let mut pin = MockPin {
+            timer,
+            levels: std::vec::Vec::new(),
+        };
+
+        ingress
+            .event
+            .enqueue(Frame::new(Event::Reset, Payload::new()))
+            .unwrap();
+
+        block_on(radio.hard_reset(&mut pin, Duration::from_millis(20))).unwrap();
+        assert_eq!(
+            pin.levels,
+            [(false, Duration::ZERO), (true, Duration::from_millis(20))]
+        );
+        assert_eq!(radio.event_len(), 0);
+    }
+
+    #[test]
+    fn ready_timeout() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio
+            .with_timer(timer.clone())
+            .with_response_timeout(Request::GetUserSetting, Duration::from_secs(1));
+
+        assert!(matches!(
+            block_on(radio.ready()),
+            Err(CommandError::Timeout)
+        ));
+        assert!(timer.now() >= READY_TIMEOUT);
+        assert!(timer.now() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn rssi_conversion() {
+        assert_eq!(rssi_dbm(0xC4), Some(-60));
+        assert_eq!(rssi_dbm(0x7F), Some(127));
+        assert_eq!(rssi_dbm(0x80), None);
+    }
+
+    #[test]
+    fn rssi_continuous() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio
+            .with_timer(timer.clone())
+            .with_response_timeout(Request::Rssi, Duration::from_millis(100));
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+
+        // each reading queues the next response, until the module stops answering.
+        let mut readings = std::vec::Vec::new();
+        let result = block_on(radio.rssi_continuous(Duration::from_millis(500), |rssi| {
+            if readings.is_empty() {
+                ingress
+                    .response
+                    .enqueue(Frame::new(Response::Rssi, payload(&[0x80])))
+                    .unwrap();
+            }
+            readings.push(rssi);
+        }));
+
+        assert!(matches!(result, Err(CommandError::Timeout)));
+        assert_eq!(readings, [Some(-60), None]);
+        assert_eq!(
+            timer.elapsed(),
+            [
+                Duration::from_millis(500),
+                Duration::from_millis(500),
+                Duration::from_millis(100)
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_scan_invalid_original() {
+        let mut written = std::vec::Vec::new();
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            Tap::new(MockSerial, |bytes: &[u8]| written.extend_from_slice(bytes)),
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let mut radio = radio.with_timer(MockTimer::default());
+
+        // runtime settings reporting channel 0, which can't be restored.
+        ingress
+            .response
+            .enqueue(Frame::new(
+                Response::GetUserSetting,
+                payload(&[0x00, 0x00, 0x0E, 0x01, 0x02, 0x03, 0x04]),
+            ))
+            .unwrap();
+
+        assert!(matches!(
+            block_on(radio.channel_scan(100..=101, Duration::from_millis(10))),
+            Err(CommandError::UnexpectedResponse)
+        ));
+        drop((radio, ingress));
+
+        // only the settings were read, the channel was left alone.
+        let mut expected = [0; 8];
+        let len = command(&mut expected, 0x0A, &[Setting::RuntimeSettings as u8]);
+        assert_eq!(written, &expected[..len]);
+    }
+
+    #[test]
+    fn send_retry_recovers() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x03], &[0x00]]);
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let timer = MockTimer::default();
+        let mut radio = radio.with_timer(timer.clone());
+
+        let backoff = Duration::from_millis(50);
+        let result = block_on_with(&mut ingress, radio.send_retry(b"hello", 3, backoff));
+
+        assert!(result.is_ok());
+        assert_eq!(module.requests(), [0x00, 0x00]);
+        assert_eq!(timer.elapsed(), [backoff]);
+    }
+
+    #[test]
+    fn send_retry_gives_up() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x03], &[0x04], &[0x03], &[0x00]]);
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let timer = MockTimer::default();
+        let mut radio = radio.with_timer(timer.clone());
+
+        let backoff = Duration::from_millis(50);
+        let result = block_on_with(&mut ingress, radio.send_retry(b"hello", 3, backoff));
+
+        // the error from the final attempt is returned, with a backoff between each attempt.
+        assert!(matches!(
+            result,
+            Err(CommandError::Send(SendDataError::ChannelBusy))
+        ));
+        assert_eq!(module.requests(), [0x00, 0x00, 0x00]);
+        assert_eq!(timer.elapsed(), [backoff, backoff]);
+    }
+
+    #[test]
+    fn send_retry_not_retryable() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x02]]);
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let timer = MockTimer::default();
+        let mut radio = radio.with_timer(timer.clone());
+
+        let backoff = Duration::from_millis(50);
+        let result = block_on_with(&mut ingress, radio.send_retry(b"hello", 3, backoff));
+
+        assert!(matches!(
+            result,
+            Err(CommandError::Send(SendDataError::InvalidChannel))
+        ));
+        assert_eq!(module.requests(), [0x00]);
+        assert!(timer.elapsed().is_empty());
+    }
+
+    #[test]
+    fn guard_time() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x00], &[0xC4], &[0xC4]]);
+        let (radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let timer = MockTimer::default();
+        let guard = Duration::from_millis(30);
+        let mut radio = radio.with_timer(timer.clone()).with_guard_time(guard);
+
+        block_on_with(&mut ingress, async {
+            radio.reset().await.unwrap();
+            radio.rssi().await.unwrap();
+            radio.rssi().await.unwrap();
+        });
+
+        // only the command following the reset waits out the guard time.
+        assert_eq!(module.requests(), [0x05, 0x0D, 0x0D]);
+        assert_eq!(timer.elapsed(), [guard]);
+    }
+
+    #[test]
+    fn guard_time_unwritten() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let guard = Duration::from_millis(30);
+        let mut radio = radio.with_timer(timer.clone()).with_guard_time(guard);
+
+        // a reset which is never written doesn't delay the next command.
+        radio.awake = false;
+        assert!(matches!(
+            block_on(radio.reset()),
+            Err(CommandError::NotAwake)
+        ));
+        assert!(!radio.guard_pending);
+
+        // a busy radio fails without first waiting out the guard time.
+        radio.awake = true;
+        radio.guard_pending = true;
+        radio.in_flight = true;
+        assert!(matches!(block_on(radio.rssi()), Err(CommandError::Busy)));
+        assert!(timer.elapsed().is_empty());
+        assert!(radio.guard_pending);
+    }
+
+    #[test]
+    fn write_within_timeout() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            SlowSerial(5),
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let timer = MockTimer::default();
+        let mut radio = radio
+            .with_timer(timer.clone())
+            .with_write_timeout(Duration::from_millis(10));
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+
+        assert!(matches!(block_on(radio.rssi()), Ok(0xC4)));
+        assert_eq!(timer.now(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn mismatched_response() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0x00])))
+            .unwrap();
+        assert!(matches!(
+            block_on(radio.reset()),
+            Err(CommandError::UnexpectedResponse)
+        ));
+    }
+
+    #[test]
+    fn send_data_ex_response() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        let mut raw = [0; 8];
+        let len = command(&mut raw, 0x40, &[0x00]);
+        ingress
+            .response
+            .enqueue(Frame::<Response>::decode(&raw[..len]).unwrap())
+            .unwrap();
+        assert!(block_on(radio.request_ack(Request::SendDataEx, &[0x03, 0x48], status_ok)).is_ok());
+    }
+
+    #[test]
+    fn send_to_payload() {
+        let mut written = std::vec::Vec::new();
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            Tap::new(MockSerial, |bytes: &[u8]| written.extend_from_slice(bytes)),
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        let mut raw = [0; 8];
+        let len = command(&mut raw, 0x40, &[0x00]);
+        ingress
+            .response
+            .enqueue(Frame::<Response>::decode(&raw[..len]).unwrap())
+            .unwrap();
+        assert!(block_on(radio.send_to(
+            AddressMode::NetworkAndAddress,
+            0x05,
+            Address::Short(0x03),
+            b"hi"
+        ))
+        .is_ok());
+        drop((radio, ingress));
+
+        let mut expected = [0; 16];
+        let len = command(&mut expected, 0x01, &[0x05, 0x03, b'h', b'i']);
+        assert_eq!(written, &expected[..len]);
+    }
+
+    #[test]
+    fn send_to_layouts() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        let result = block_on_with(
+            &mut ingress,
+            radio.send_to(
+                AddressMode::NetworkAndLongAddress,
+                0x05,
+                Address::Long(0x1234),
+                b"hi",
+            ),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(module.requests(), [0x01]);
+        assert_eq!(module.last_request(), [0x05, 0x34, 0x12, b'h', b'i']);
+
+        // without a network id in the mode, only the address is sent.
+        let result = block_on_with(
+            &mut ingress,
+            radio.send_to(AddressMode::Address, 0x05, Address::Short(0x03), b"hi"),
+        );
+
+        assert!(result.is_ok());
+        assert_eq!(module.last_request(), [0x03, b'h', b'i']);
+    }
+
+    #[test]
+    fn set_destination() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        module.0.borrow_mut().settings.insert(
+            Setting::RuntimeSettings as u8,
+            std::vec![0x70, 0x0E, 0x01, 0x02, 0x03, 0x04],
+        );
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        block_on_with(&mut ingress, radio.set_destination(0x12, 0x34)).unwrap();
+        assert_eq!(
+            module.requests(),
+            [
+                Request::SetDestinationNetworkId as u8,
+                Request::SetDestinationAddress as u8
+            ]
+        );
+        assert_eq!(module.last_request(), [0x34]);
+
+        // read from the runtime settings block, which the mock module doesn't update.
+        assert_eq!(
+            block_on_with(&mut ingress, radio.destination()).unwrap(),
+            (0x01, 0x02)
+        );
+    }
+
+    #[test]
+    fn set_destination_net_rejected() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x01]]);
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        // the address is left alone once setting the net fails.
+        assert!(matches!(
+            block_on_with(&mut ingress, radio.set_destination(0x12, 0x34)),
+            Err(CommandError::Status(0x01))
+        ));
+        assert_eq!(module.requests(), [Request::SetDestinationNetworkId as u8]);
+    }
+
+    #[test]
+    fn send_without_acks() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::with_replies(&[&[0x01], &[0x01]]);
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        assert!(radio.acks_enabled());
+        assert!(matches!(
+            block_on_with(&mut ingress, radio.send(b"hi")),
+            Err(CommandError::Send(SendDataError::AckTimeout))
+        ));
+
+        block_on_with(&mut ingress, radio.set_mac_retries(0)).unwrap();
+        assert_eq!(module.setting(Setting::RetryNumbers).unwrap(), [0x00]);
+        assert!(!radio.acks_enabled());
+        block_on_with(&mut ingress, radio.send(b"hi")).unwrap();
+
+        block_on_with(&mut ingress, radio.set_mac_retries(3)).unwrap();
+        radio.assume_acks(false);
+        assert_eq!(block_on_with(&mut ingress, radio.mac_retries()).unwrap(), 3);
+        assert!(radio.acks_enabled());
+    }
+
+    #[test]
+    fn set_lbt() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        module
+            .0
+            .borrow_mut()
+            .settings
+            .insert(Setting::ConfigFlags as u8, std::vec![0x01, 0x00]);
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+
+        // only the listen before talk flag changes, the other flags are kept.
+        block_on_with(&mut ingress, radio.set_lbt(true)).unwrap();
+        assert_eq!(module.setting(Setting::ConfigFlags).unwrap(), [0x11, 0x00]);
+        assert_eq!(
+            block_on_with(&mut ingress, radio.config_flags()).unwrap(),
+            ConfigFlags::from_bits(0x0011)
+        );
+
+        block_on_with(&mut ingress, radio.set_lbt(false)).unwrap();
+        assert_eq!(module.setting(Setting::ConfigFlags).unwrap(), [0x01, 0x00]);
+    }
+
+    #[test]
+    fn long_address_settings() {
+        let mut storage = RadioStorage::new();
+        let module = MockModule::default();
+        let (mut radio, mut ingress) =
+            Radio::from_storage(module.clone(), module.clone(), &mut storage);
+        let long = AddressMode::NetworkAndLongAddress;
+
+        block_on_with(&mut ingress, async {
+            radio
+                .set_source_address(Address::Long(0x1234))
+                .await
+                .unwrap();
+            radio
+                .set_default_destination_address(Address::Long(0xABCD))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                radio.source_address(long).await.unwrap(),
+                Address::Long(0x1234)
+            );
+            assert_eq!(
+                radio.default_destination_address(long).await.unwrap(),
+                Address::Long(0xABCD)
+            );
+            assert_eq!(
+                radio
+                    .source_address(AddressMode::NetworkAndAddress)
+                    .await
+                    .unwrap(),
+                Address::Short(0x34)
+            );
+        });
+
+        assert_eq!(module.setting(Setting::SourceAddr).unwrap(), [0x34]);
+        assert_eq!(module.setting(Setting::SourceAddrMsb).unwrap(), [0x12]);
+        assert_eq!(
+            module.setting(Setting::DefaultDestinationAddr).unwrap(),
+            [0xCD]
+        );
+        assert_eq!(
+            module.setting(Setting::DefaultDestinationAddrMsb).unwrap(),
+            [0xAB]
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let err = CommandError::<ErrorKind>::Send(SendDataError::ChannelBusy);
+        assert_eq!(err.to_string(), "send failed: channel busy");
+
+        let err = ConfigureError {
+            step: ConfigureStep::DestinationNet,
+            error: CommandError::<ErrorKind>::Timeout,
+        };
+        assert_eq!(
+            err.to_string(),
+            "setting destination network id failed: timed out waiting for response"
+        );
+
+        let err = IngestError::<ErrorKind>::Decode(DecodeError::UnknownCommand(0x46));
+        assert_eq!(err.to_string(), "invalid frame: unknown command 0x46");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn error_source() {
+        use std::error::Error;
+
+        let io = std::io::Error::other("unplugged");
+        let err = CommandError::Io(io);
+        assert_eq!(err.source().unwrap().to_string(), "unplugged");
+
+        let err = IngestError::<std::io::Error>::Decode(DecodeError::Checksum);
+        assert_eq!(err.source().unwrap().to_string(), "checksum mismatch");
+        assert!(IngestError::<std::io::Error>::Closed.source().is_none());
+    }
+
+    #[test]
+    fn send_too_long() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        assert!(matches!(
+            poll_once(radio.send(&[0; MAX_DATA_LEN + 1])),
+            Poll::Ready(Err(CommandError::Send(SendDataError::PayloadInvalid)))
+        ));
+        assert!(!radio.in_flight);
+    }
+
+    #[test]
+    fn send_to_too_long() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        let mode = AddressMode::NetworkAndLongAddress;
+        let data = [0; AddressMode::NetworkAndLongAddress.max_data_len() + 1];
+        assert!(matches!(
+            poll_once(radio.send_to(mode, 0x05, Address::Long(0x1234), &data)),
+            Poll::Ready(Err(CommandError::Send(SendDataError::PayloadInvalid)))
+        ));
+    }
+
+    #[test]
+    fn send_to_invalid_address() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        for (mode, address) in [
+            (AddressMode::None, Address::Short(0x03)),
+            (AddressMode::NetworkAndAddress, Address::Long(0x1234)),
+            (AddressMode::NetworkAndLongAddress, Address::Short(0x03)),
+        ] {
+            assert!(matches!(
+                poll_once(radio.send_to(mode, 0x05, address, b"hi")),
+                Poll::Ready(Err(CommandError::InvalidAddress))
+            ));
+        }
+    }
+
+    #[test]
+    fn set_user_setting_length() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, _ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        for (setting, value) in [
+            (Setting::DefaultRfChannel, &[108, 0][..]),
+            (Setting::ConfigFlags, &[0x01][..]),
+            (Setting::FirmwareVersion, &[1, 2, 3][..]),
+        ] {
+            assert!(matches!(
+                block_on(radio.set_user_setting(setting, value)),
+                Err(CommandError::InvalidSetting)
+            ));
+        }
+        assert!(!radio.is_awaiting_response());
+    }
+
+    #[test]
+    fn stale_response_discard() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+        let mut radio = radio.with_stale_response_discard();
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+        assert!(poll_once(radio.rssi()).is_pending());
+        assert_eq!(radio.response_len(), 0);
+    }
+
+    #[test]
+    fn empty_response() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, Payload::new()))
+            .unwrap();
+        assert!(matches!(
+            block_on(radio.rssi()),
+            Err(CommandError::UnexpectedResponse)
+        ));
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Reset, Payload::new()))
+            .unwrap();
+        assert!(matches!(
+            block_on(radio.reset()),
+            Err(CommandError::UnexpectedResponse)
+        ));
+    }
+
+    #[test]
+    fn configure_failed_step() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::SetChannel, payload(&[0x00])))
+            .unwrap();
+        let err = block_on(radio.configure(0x01, 0x02, Channel::new(0x6C).unwrap())).unwrap_err();
+        assert_eq!(err.step, ConfigureStep::Channel);
+        assert!(matches!(err.error, CommandError::Status(0x00)));
+    }
+
+    #[test]
+    fn send_raw_response() {
+        let mut response_queue = Queue::new();
+        let mut event_queue = Queue::new();
+        let (mut radio, mut ingress) = Radio::new(
+            MockSerial,
+            MockSerial,
+            &mut response_queue,
+            &mut event_queue,
+        );
+
+        ingress
+            .response
+            .enqueue(Frame::new(Response::Rssi, payload(&[0xC4])))
+            .unwrap();
+        let response = block_on(radio.send_raw(0x0D, &[])).unwrap();
+        assert_eq!(*response.command(), Response::Rssi);
+        assert_eq!(response.data(), &[0xC4]);
+    }
+
+    #[test]
+    fn decode_typed_frames() {
+        let frame = Frame::<Response>::decode(&[START, 0x46, 0x01, 0x6C, 0x29]).unwrap();
+        assert_eq!(*frame.command(), Response::SetChannel);
+        assert_eq!(frame.data(), &[0x6C]);
+
+        assert_eq!(
+            Frame::<Event>::decode(&[START, 0x46, 0x01, 0x6C, 0x29]).err(),
+            Some(DecodeError::UnknownCommand(0x46))
+        );
+    }
 }
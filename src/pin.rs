@@ -0,0 +1,12 @@
+/// Output pin driving the module's reset line.
+///
+/// Mirrors `embedded_hal::digital::OutputPin` so HAL pins can be wrapped with little effort.
+pub trait ResetPin {
+    type Error;
+
+    /// Drive the reset line low, holding the module in reset.
+    fn set_low(&mut self) -> Result<(), Self::Error>;
+
+    /// Drive the reset line high, releasing the module from reset.
+    fn set_high(&mut self) -> Result<(), Self::Error>;
+}
@@ -1,21 +1,460 @@
+use core::fmt;
+use core::time::Duration;
+
+use crate::command::MAX_DATA_LEN;
+
 /// User setting index.
-#[derive(Debug)]
+///
+/// Values are single bytes unless noted, multi-byte values are little endian.
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
-enum Setting {
+pub enum Setting {
+    /// UART baud rate.
+    ///
+    /// Parity and stop bits aren't settings, the UART always uses 8 data bits, no parity and
+    /// one stop bit. A new baud rate takes effect after a reset, after which the host port must
+    /// be reopened at the same rate.
     UartBaudRate = 0x00,
+    /// Default RF profile (see [`RfProfile`]).
     DefaultRfProfile = 0x01,
+    /// Default radio transmit power.
     DefaultRfTxPower = 0x02,
+    /// Default RF channel.
     DefaultRfChannel = 0x03,
+    /// Default address mode.
     DefaultAddressMode = 0x04,
+    /// Number of MAC retries.
     RetryNumbers = 0x06,
+    /// Default destination network id.
     DefaultDestinationNetId = 0x07,
+    /// Default destination address, the low byte of a long address.
     DefaultDestinationAddr = 0x08,
+    /// High byte of the default destination address, used by
+    /// [`AddressMode::NetworkAndLongAddress`].
+    DefaultDestinationAddrMsb = 0x09,
+    /// Source network id.
     SourceNetId = 0x0A,
+    /// Source address, the low byte of a long address.
     SourceAddr = 0x0B,
+    /// High byte of the source address, used by [`AddressMode::NetworkAndLongAddress`].
+    SourceAddrMsb = 0x0C,
+    /// Configuration flags, a `u16` (see [`ConfigFlags`]).
     ConfigFlags = 0x0F,
+    /// Repeater flags.
     RpFlags = 0x10,
+    /// Number of repeater slots.
     RpNumSlots = 0x11,
+    /// Factory settings (read only), starting with the serial number as a `u32`.
     FactorySettings = 0x20,
+    /// Firmware version (read only), see [`FirmwareVersion`].
     FirmwareVersion = 0x21,
+    /// Current runtime settings (read only).
     RuntimeSettings = 0x22,
 }
+
+impl Setting {
+    /// Length in bytes of the setting's value, or [`None`] if the setting is read only.
+    pub const fn value_len(self) -> Option<usize> {
+        match self {
+            Self::ConfigFlags => Some(2),
+            Self::FactorySettings | Self::FirmwareVersion | Self::RuntimeSettings => None,
+            _ => Some(1),
+        }
+    }
+}
+
+/// RF profile, selecting the modulation and data rate.
+///
+/// All nodes in a network must use the same profile to communicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum RfProfile {
+    /// Profile 0, 38.4 kbit/s (factory default).
+    Rate38k4 = 0x00,
+    /// Profile 1, 100 kbit/s.
+    Rate100k = 0x01,
+    /// Profile 2, 250 kbit/s.
+    Rate250k = 0x02,
+    /// Profile 3, 1.2 kbit/s long range.
+    Rate1k2 = 0x03,
+}
+
+impl RfProfile {
+    pub fn try_from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0x00 => Some(Self::Rate38k4),
+            0x01 => Some(Self::Rate100k),
+            0x02 => Some(Self::Rate250k),
+            0x03 => Some(Self::Rate1k2),
+            _ => None,
+        }
+    }
+
+    /// Over the air data rate in bits per second.
+    pub const fn data_rate(self) -> u32 {
+        match self {
+            Self::Rate38k4 => 38_400,
+            Self::Rate100k => 100_000,
+            Self::Rate250k => 250_000,
+            Self::Rate1k2 => 1_200,
+        }
+    }
+
+    /// Time to transmit `len` bytes of packet at this profile's data rate.
+    ///
+    /// The manual doesn't give the preamble, sync word and CRC added by the radio, so this is a
+    /// lower bound on the air time of a packet carrying `len` bytes. Schedules should leave a
+    /// margin on top of it, as well as time for any ACK and retries.
+    pub const fn air_time(self, len: usize) -> Duration {
+        let bits = len as u64 * 8;
+        Duration::from_nanos(bits * 1_000_000_000 / self.data_rate() as u64)
+    }
+}
+
+/// RF channel number.
+///
+/// Only channels within the module's 868 MHz band are valid, so a checked channel can't be
+/// rejected with [`SendDataError::InvalidChannel`](crate::SendDataError::InvalidChannel).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// Lowest valid channel.
+    pub const MIN: u8 = 100;
+    /// Highest valid channel.
+    pub const MAX: u8 = 140;
+
+    /// Returns [`None`] if the channel is out of range.
+    pub const fn new(channel: u8) -> Option<Self> {
+        if channel >= Self::MIN && channel <= Self::MAX {
+            Some(Self(channel))
+        } else {
+            None
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// Address mode, selecting which addressing fields are sent with each packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AddressMode {
+    /// No addressing, packets are broadcast.
+    None = 0x00,
+    /// Destination address only.
+    Address = 0x01,
+    /// Destination network id and address.
+    NetworkAndAddress = 0x02,
+    /// Destination network id and two byte address.
+    NetworkAndLongAddress = 0x03,
+}
+
+impl AddressMode {
+    pub fn try_from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0x00 => Some(Self::None),
+            0x01 => Some(Self::Address),
+            0x02 => Some(Self::NetworkAndAddress),
+            0x03 => Some(Self::NetworkAndLongAddress),
+            _ => None,
+        }
+    }
+
+    /// Number of addressing bytes prepended to the data of an explicitly addressed send.
+    pub const fn overhead(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Address => 1,
+            Self::NetworkAndAddress => 2,
+            Self::NetworkAndLongAddress => 3,
+        }
+    }
+
+    /// Number of bytes used for the node address in this mode.
+    pub const fn address_len(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Address | Self::NetworkAndAddress => 1,
+            Self::NetworkAndLongAddress => 2,
+        }
+    }
+
+    /// Maximum user data length of an explicitly addressed send in this mode.
+    pub const fn max_data_len(self) -> usize {
+        MAX_DATA_LEN - self.overhead()
+    }
+}
+
+/// Node address, in the form used by the configured [`AddressMode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Address {
+    /// One byte address.
+    Short(u8),
+    /// Two byte address, used by [`AddressMode::NetworkAndLongAddress`].
+    Long(u16),
+}
+
+impl Address {
+    /// Parse an address of the size used by `mode` from the start of `raw`.
+    ///
+    /// Long addresses are little endian. Returns [`None`] if the mode has no address or `raw`
+    /// is too short.
+    pub fn from_raw(mode: AddressMode, raw: &[u8]) -> Option<Self> {
+        match (mode.address_len(), raw) {
+            (1, [address, ..]) => Some(Self::Short(*address)),
+            (2, [low, high, ..]) => Some(Self::Long(u16::from_le_bytes([*low, *high]))),
+            _ => None,
+        }
+    }
+
+    /// Encode the address into `buf`, returning the number of bytes written.
+    ///
+    /// Panics if `buf` is too short.
+    pub fn encode(self, buf: &mut [u8]) -> usize {
+        match self {
+            Self::Short(address) => {
+                buf[0] = address;
+                1
+            }
+            Self::Long(address) => {
+                buf[..2].copy_from_slice(&address.to_le_bytes());
+                2
+            }
+        }
+    }
+}
+
+/// Repeater flags, as stored in [`Setting::RpFlags`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RepeaterFlags(u8);
+
+impl RepeaterFlags {
+    /// No repeater functionality.
+    pub const NONE: Self = Self(0x00);
+    /// Repeat received packets.
+    pub const ENABLE: Self = Self(0x01);
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for RepeaterFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Configuration flags, as stored in [`Setting::ConfigFlags`].
+///
+/// Stored on the module as a little endian `u16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConfigFlags(u16);
+
+impl ConfigFlags {
+    /// No flags set.
+    pub const NONE: Self = Self(0x0000);
+    /// Listen before talk, the channel must be clear before a packet is transmitted.
+    pub const LISTEN_BEFORE_TALK: Self = Self(0x0010);
+
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Set or clear the flags in `other`.
+    pub const fn set(self, other: Self, enabled: bool) -> Self {
+        if enabled {
+            Self(self.0 | other.0)
+        } else {
+            Self(self.0 & !other.0)
+        }
+    }
+}
+
+impl core::ops::BitOr for ConfigFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Live configuration of the module, as read from [`Setting::RuntimeSettings`].
+///
+/// The operating mode isn't included, and no other command reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeSettings {
+    /// RF channel.
+    pub channel: u8,
+    /// Radio transmit power.
+    pub tx_power: u8,
+    /// Destination network id.
+    pub destination_net: u8,
+    /// Destination address.
+    pub destination_address: u8,
+    /// Source network id.
+    pub source_net: u8,
+    /// Source address.
+    pub source_address: u8,
+}
+
+impl RuntimeSettings {
+    /// Length of the runtime settings block.
+    pub const LEN: usize = 6;
+
+    /// Parse the runtime settings block.
+    ///
+    /// Layout is channel, transmit power, destination network id, destination address, source
+    /// network id and source address, one byte each. Returns [`None`] if the block is too short.
+    pub fn from_raw(raw: &[u8]) -> Option<Self> {
+        if raw.len() < Self::LEN {
+            return None;
+        }
+
+        Some(Self {
+            channel: raw[0],
+            tx_power: raw[1],
+            destination_net: raw[2],
+            destination_address: raw[3],
+            source_net: raw[4],
+            source_address: raw[5],
+        })
+    }
+}
+
+/// Module firmware version, as read from [`Setting::FirmwareVersion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl FirmwareVersion {
+    /// Parse the firmware version setting.
+    ///
+    /// Layout is major, minor and patch, one byte each. Returns [`None`] if the value is too
+    /// short.
+    pub fn from_raw(raw: &[u8]) -> Option<Self> {
+        match raw {
+            [major, minor, patch, ..] => Some(Self {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Read a little endian `u16` from the start of a setting value.
+pub(crate) fn le_u16(raw: &[u8]) -> Option<u16> {
+    Some(u16::from_le_bytes(raw.get(..2)?.try_into().ok()?))
+}
+
+/// Read a little endian `u32` from the start of a setting value.
+pub(crate) fn le_u32(raw: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(raw.get(..4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_mode_max_data_len() {
+        assert_eq!(AddressMode::None.max_data_len(), 220);
+        assert_eq!(AddressMode::Address.max_data_len(), 219);
+        assert_eq!(AddressMode::NetworkAndAddress.max_data_len(), 218);
+        assert_eq!(AddressMode::NetworkAndLongAddress.max_data_len(), 217);
+    }
+
+    #[test]
+    fn address_encoding() {
+        let mut buf = [0; 2];
+
+        assert_eq!(Address::Long(0x1234).encode(&mut buf), 2);
+        assert_eq!(buf, [0x34, 0x12]);
+        assert_eq!(
+            Address::from_raw(AddressMode::NetworkAndLongAddress, &buf),
+            Some(Address::Long(0x1234))
+        );
+        assert_eq!(
+            Address::from_raw(AddressMode::Address, &buf),
+            Some(Address::Short(0x34))
+        );
+        assert_eq!(Address::from_raw(AddressMode::None, &buf), None);
+    }
+
+    #[test]
+    fn runtime_settings_parse() {
+        let settings = RuntimeSettings::from_raw(&[0x6C, 0x0E, 0x01, 0x02, 0x03, 0x04]).unwrap();
+
+        assert_eq!(settings.channel, 0x6C);
+        assert_eq!(settings.tx_power, 0x0E);
+        assert_eq!(settings.destination_net, 0x01);
+        assert_eq!(settings.destination_address, 0x02);
+        assert_eq!(settings.source_net, 0x03);
+        assert_eq!(settings.source_address, 0x04);
+
+        assert_eq!(RuntimeSettings::from_raw(&[0x6C, 0x0E]), None);
+    }
+
+    #[test]
+    fn firmware_version_parse() {
+        let version = FirmwareVersion::from_raw(&[0x01, 0x04, 0x00]).unwrap();
+
+        assert_eq!(version.to_string(), "1.4.0");
+        assert_eq!(FirmwareVersion::from_raw(&[0x01]), None);
+    }
+
+    #[test]
+    fn little_endian_values() {
+        assert_eq!(le_u16(&[0x10, 0x00, 0xFF]), Some(0x0010));
+        assert_eq!(le_u32(&[0x78, 0x56, 0x34, 0x12]), Some(0x1234_5678));
+        assert_eq!(le_u32(&[0x78, 0x56]), None);
+    }
+
+    #[test]
+    fn profile_air_time() {
+        assert_eq!(RfProfile::Rate1k2.air_time(3), Duration::from_millis(20));
+        assert_eq!(RfProfile::Rate250k.air_time(125), Duration::from_millis(4));
+        assert_eq!(RfProfile::Rate38k4.air_time(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn channel_range() {
+        assert_eq!(Channel::new(99), None);
+        assert_eq!(Channel::new(108).map(Channel::get), Some(108));
+        assert_eq!(Channel::new(141), None);
+    }
+}
@@ -0,0 +1,383 @@
+//! Storage shared between a radio and its ingress.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::Waker;
+use heapless::spsc::{Consumer, Producer, Queue};
+
+use crate::{Event, Frame, Response};
+
+/// Queues and state shared by a [`Radio`](crate::Radio) and its [`Ingress`](crate::Ingress).
+///
+/// Unlike bare queues this lets the radio detect that its ingress has been dropped, and lets
+/// the ingress follow mode changes, see [`Radio::from_storage`](crate::Radio::from_storage).
+///
+/// Each module needs its own storage, which must outlive both halves. To drive several modules
+/// give each one a storage of its own, for example an array of storages in a `static` cell on
+/// embedded targets, or a leaked `Box` per module when the ingress runs in a spawned task.
+/// Nothing is shared between instances, so they need no synchronisation with each other.
+///
+/// `EVENTS` sets the size of the event queue, which holds one fewer event than its size. Nodes
+/// which only transmit can use the smallest queue, [`RadioStorage::with_event_queue`] with a
+/// size of 2, to save RAM. Events which don't fit are dropped by the ingress, which must still
+/// run to receive responses, and counted in
+/// [`Radio::dropped_frames`](crate::Radio::dropped_frames). Which events are dropped is chosen
+/// with [`RadioStorage::with_overflow_policy`].
+pub struct RadioStorage<const EVENTS: usize = 16> {
+    pub(crate) response: Queue<Frame<Response>, 2>,
+    pub(crate) event: EventCell<EVENTS>,
+    pub(crate) state: SharedState,
+    pub(crate) policy: OverflowPolicy,
+}
+
+impl RadioStorage {
+    pub const fn new() -> Self {
+        Self::with_event_queue()
+    }
+}
+
+impl<const EVENTS: usize> RadioStorage<EVENTS> {
+    /// Create storage with an event queue of size `EVENTS`, for example
+    /// `RadioStorage::<2>::with_event_queue()`.
+    pub const fn with_event_queue() -> Self {
+        Self {
+            response: Queue::new(),
+            event: EventCell(UnsafeCell::new(Queue::new())),
+            state: SharedState::new(),
+            policy: OverflowPolicy::DropNewest,
+        }
+    }
+
+    /// Choose which events are dropped once the event queue is full.
+    pub const fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Default for RadioStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Events dropped by the ingress once the event queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Keep the queued events and drop the new one.
+    #[default]
+    DropNewest,
+    /// Drop the oldest queued event to make room for the new one, so the queue holds the latest
+    /// events.
+    ///
+    /// The radio and ingress then take turns at the queue, rather than each owning one end. The
+    /// radio misses an event it would have taken while the ingress is queuing one, and is woken
+    /// again once the ingress is done.
+    DropOldest,
+}
+
+/// Event queue, taken in turns by the radio and ingress for [`OverflowPolicy::DropOldest`].
+pub(crate) struct EventCell<const N: usize>(UnsafeCell<Queue<Frame<Event>, N>>);
+
+// SAFETY: once shared the queue is only accessed holding the event lock, see
+// `EventCell::with_queue`.
+unsafe impl<const N: usize> Sync for EventCell<N> {}
+
+impl<const N: usize> EventCell<N> {
+    pub(crate) fn get_mut(&mut self) -> &mut Queue<Frame<Event>, N> {
+        self.0.get_mut()
+    }
+
+    /// Access the queue holding the event lock, or return [`None`] if the other half holds it.
+    fn with_queue<R>(
+        &self,
+        state: &SharedState,
+        side: Side,
+        f: impl FnOnce(&mut Queue<Frame<Event>, N>) -> R,
+    ) -> Option<R> {
+        // SAFETY: the lock is held, so the other half isn't accessing the queue.
+        state.lock_events(side, || f(unsafe { &mut *self.0.get() }))
+    }
+}
+
+/// Radio end of the event queue.
+pub(crate) enum EventConsumer<'a, const N: usize> {
+    Split(Consumer<'a, Frame<Event>, N>),
+    Shared(&'a EventCell<N>, &'a SharedState),
+}
+
+impl<const N: usize> EventConsumer<'_, N> {
+    /// Take the oldest event, if any.
+    ///
+    /// Returns [`None`] while the ingress is queuing an event, which wakes the radio once done.
+    pub(crate) fn dequeue(&mut self) -> Option<Frame<Event>> {
+        match self {
+            Self::Split(consumer) => consumer.dequeue(),
+            Self::Shared(cell, state) => cell.with_queue(state, Side::Radio, Queue::dequeue)?,
+        }
+    }
+
+    /// Number of queued events, counted as zero while the ingress is queuing one.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Split(consumer) => consumer.len(),
+            Self::Shared(cell, state) => cell
+                .with_queue(state, Side::Radio, |queue| queue.len())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Ingress end of the event queue.
+pub(crate) enum EventProducer<'a, const N: usize> {
+    Split(Producer<'a, Frame<Event>, N>),
+    Shared(&'a EventCell<N>, &'a SharedState),
+}
+
+impl<const N: usize> EventProducer<'_, N> {
+    /// Queue an event, following the overflow policy if the queue is full.
+    ///
+    /// Returns an error if an event was dropped, either this one or the oldest queued one.
+    pub(crate) fn enqueue(&mut self, frame: Frame<Event>) -> Result<(), ()> {
+        match self {
+            Self::Split(producer) => producer.enqueue(frame).map_err(drop),
+            Self::Shared(cell, state) => {
+                let evicted = cell.with_queue(state, Side::Ingress, |queue| {
+                    let evicted = queue.is_full() && queue.dequeue().is_some();
+                    // there's room now, so this can't fail.
+                    queue.enqueue(frame).ok();
+                    evicted
+                });
+
+                match evicted {
+                    Some(false) => Ok(()),
+                    // the oldest event was evicted, or the radio held on to the queue for too
+                    // long to queue this one.
+                    Some(true) | None => Err(()),
+                }
+            }
+        }
+    }
+
+    /// Number of queued events, counted as zero while the radio is taking one.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Self::Split(producer) => producer.len(),
+            Self::Shared(cell, state) => cell
+                .with_queue(state, Side::Ingress, |queue| queue.len())
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Half taking the event lock, see [`SharedState::lock_events`].
+#[derive(Clone, Copy)]
+pub(crate) enum Side {
+    Radio,
+    Ingress,
+}
+
+/// Times the ingress checks again for the radio to release the event lock before giving up.
+const EVENT_LOCK_SPINS: usize = 64;
+
+/// Flags shared by a radio and its ingress.
+pub(crate) struct SharedState {
+    ingress_dropped: AtomicBool,
+    transparent: AtomicBool,
+    /// Mirrors the ingress's count, which is its only writer.
+    dropped_frames: AtomicUsize,
+    /// Raised by each half while taking or holding the event lock.
+    radio_events: AtomicBool,
+    ingress_events: AtomicBool,
+    /// Woken when the module leaves transparent mode.
+    pub(crate) ingress_waker: WakerSlot,
+    /// Woken when the ingress queues a frame or is dropped.
+    pub(crate) radio_waker: WakerSlot,
+}
+
+impl SharedState {
+    const fn new() -> Self {
+        Self {
+            ingress_dropped: AtomicBool::new(false),
+            transparent: AtomicBool::new(false),
+            dropped_frames: AtomicUsize::new(0),
+            radio_events: AtomicBool::new(false),
+            ingress_events: AtomicBool::new(false),
+            ingress_waker: WakerSlot::new(),
+            radio_waker: WakerSlot::new(),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.ingress_dropped.store(false, Ordering::Relaxed);
+        self.transparent.store(false, Ordering::Relaxed);
+        self.dropped_frames.store(0, Ordering::Relaxed);
+    }
+
+    pub(crate) fn ingress_dropped(&self) -> bool {
+        self.ingress_dropped.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn transparent(&self) -> bool {
+        self.transparent.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn dropped_frames(&self) -> usize {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_dropped_frames(&self, dropped: usize) {
+        self.dropped_frames.store(dropped, Ordering::Relaxed);
+    }
+
+    /// Run `f` holding the event lock, or return [`None`] if the other half holds it.
+    ///
+    /// Each half raises its own flag before checking the other's, so with sequentially
+    /// consistent ordering at least one of them sees the other and they never both hold the
+    /// lock. Only loads and stores are used, so this works without compare and swap. Neither
+    /// half waits indefinitely, as the other may be preempted by it: the radio backs off
+    /// straight away and the ingress only checks again a few times.
+    pub(crate) fn lock_events<R>(&self, side: Side, f: impl FnOnce() -> R) -> Option<R> {
+        let (mine, other, mut spins) = match side {
+            Side::Radio => (&self.radio_events, &self.ingress_events, 0),
+            Side::Ingress => (&self.ingress_events, &self.radio_events, EVENT_LOCK_SPINS),
+        };
+
+        mine.store(true, Ordering::SeqCst);
+        while other.load(Ordering::SeqCst) {
+            if spins == 0 {
+                mine.store(false, Ordering::SeqCst);
+                return None;
+            }
+            spins -= 1;
+            core::hint::spin_loop();
+        }
+
+        let result = f();
+        mine.store(false, Ordering::SeqCst);
+        Some(result)
+    }
+
+    pub(crate) fn set_transparent(&self, transparent: bool) {
+        self.transparent.store(transparent, Ordering::Release);
+        if !transparent {
+            self.ingress_waker.wake();
+        }
+    }
+}
+
+/// Flags the ingress as dropped when it goes out of scope.
+pub(crate) struct IngressGuard<'a>(pub(crate) Option<&'a SharedState>);
+
+impl Drop for IngressGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = self.0 {
+            state.ingress_dropped.store(true, Ordering::Release);
+            state.radio_waker.wake();
+        }
+    }
+}
+
+/// Waker of a task waiting on the other half, registered by the waiting side and woken by the
+/// other.
+///
+/// Only one task may wait on a slot at a time, which holds as each slot is only waited on by one
+/// half. The waiter must check its condition again after registering, so a wake between the
+/// check and the registration isn't lost. On targets without atomic compare and swap the waiter
+/// is woken straight away instead, so it polls until the condition holds.
+#[cfg(target_has_atomic = "8")]
+pub(crate) struct WakerSlot {
+    state: core::sync::atomic::AtomicU8,
+    waker: core::cell::UnsafeCell<Option<Waker>>,
+}
+
+#[cfg(target_has_atomic = "8")]
+mod slot_state {
+    /// No registration or wake is in progress.
+    pub(super) const IDLE: u8 = 0;
+    /// The waiter is replacing the waker.
+    pub(super) const REGISTERING: u8 = 0b01;
+    /// The other half is taking the waker.
+    pub(super) const WAKING: u8 = 0b10;
+}
+
+// SAFETY: the waker is only accessed by whichever side moved the state away from `IDLE`, so it's
+// never accessed concurrently.
+#[cfg(target_has_atomic = "8")]
+unsafe impl Sync for WakerSlot {}
+
+#[cfg(target_has_atomic = "8")]
+impl WakerSlot {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: core::sync::atomic::AtomicU8::new(slot_state::IDLE),
+            waker: core::cell::UnsafeCell::new(None),
+        }
+    }
+
+    /// Register the waker to be woken by [`WakerSlot::wake`].
+    pub(crate) fn register(&self, waker: &Waker) {
+        use slot_state::*;
+
+        match self
+            .state
+            .compare_exchange(IDLE, REGISTERING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                // SAFETY: the state is `REGISTERING`, so the other half won't touch the waker
+                // until it's set back to `IDLE`.
+                let slot = unsafe { &mut *self.waker.get() };
+                match slot {
+                    Some(old) if old.will_wake(waker) => {}
+                    _ => *slot = Some(waker.clone()),
+                }
+
+                if self
+                    .state
+                    .compare_exchange(REGISTERING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    // woken while registering, so the wake falls to us.
+                    let waker = slot.take();
+                    self.state.store(IDLE, Ordering::Release);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            // a wake is in progress, so poll again rather than miss it.
+            Err(_) => waker.wake_by_ref(),
+        }
+    }
+
+    /// Wake the registered waker, if any.
+    pub(crate) fn wake(&self) {
+        use slot_state::*;
+
+        if self.state.fetch_or(WAKING, Ordering::AcqRel) == IDLE {
+            // SAFETY: the state was `IDLE` and is now `WAKING`, so the waiter won't touch the
+            // waker until it's cleared again.
+            let waker = unsafe { (*self.waker.get()).take() };
+            self.state.fetch_and(!WAKING, Ordering::Release);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(not(target_has_atomic = "8"))]
+pub(crate) struct WakerSlot;
+
+#[cfg(not(target_has_atomic = "8"))]
+impl WakerSlot {
+    pub(crate) const fn new() -> Self {
+        Self
+    }
+
+    pub(crate) fn register(&self, waker: &Waker) {
+        waker.wake_by_ref();
+    }
+
+    pub(crate) fn wake(&self) {}
+}
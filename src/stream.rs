@@ -0,0 +1,43 @@
+//! [`Stream`] adapter for received events.
+
+use crate::{poll_dequeue, Event, Frame, Radio};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use embedded_io_async::Write;
+use futures_core::Stream;
+
+/// Stream of received events, see [`Radio::poll_events`].
+///
+/// The stream never ends.
+pub struct EventStream<'r, 'a, W, T, const EVENTS: usize>
+where
+    W: Write,
+{
+    radio: &'r mut Radio<'a, W, T, EVENTS>,
+}
+
+impl<'r, 'a, W, T, const EVENTS: usize> EventStream<'r, 'a, W, T, EVENTS>
+where
+    W: Write,
+{
+    pub(crate) fn new(radio: &'r mut Radio<'a, W, T, EVENTS>) -> Self {
+        Self { radio }
+    }
+}
+
+impl<W, T, const EVENTS: usize> Stream for EventStream<'_, '_, W, T, EVENTS>
+where
+    W: Write,
+{
+    type Item = Frame<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let radio = &mut self.get_mut().radio;
+        match poll_dequeue(&mut radio.event, radio.state, cx) {
+            Poll::Ready(Some(event)) => Poll::Ready(Some(event)),
+            // events keep the stream waiting once the ingress has been dropped, as for
+            // `poll_event`.
+            _ => Poll::Pending,
+        }
+    }
+}
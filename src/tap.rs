@@ -0,0 +1,57 @@
+//! Raw byte capture.
+
+use embedded_io_async::{ErrorType, Read, Write};
+
+/// Serial wrapper mirroring every byte read or written to a callback.
+///
+/// Wrap the writer passed to [`Radio`](crate::Radio), the reader passed to its
+/// [`Ingress`](crate::Ingress), or both, to capture a raw byte log of a session. The bytes are
+/// passed through unchanged, so parsing isn't affected.
+///
+/// ```
+/// # use wurth_telesto::Tap;
+/// let mut log = Vec::new();
+/// let reader = Tap::new([0x02u8, 0x81].as_slice(), |bytes: &[u8]| log.extend_from_slice(bytes));
+/// ```
+pub struct Tap<S, F> {
+    inner: S,
+    sink: F,
+}
+
+impl<S, F: FnMut(&[u8])> Tap<S, F> {
+    /// Wrap `inner`, calling `sink` with the bytes of each successful read or write.
+    pub fn new(inner: S, sink: F) -> Self {
+        Self { inner, sink }
+    }
+
+    /// Unwrap the serial and callback.
+    pub fn into_inner(self) -> (S, F) {
+        (self.inner, self.sink)
+    }
+}
+
+impl<S: ErrorType, F> ErrorType for Tap<S, F> {
+    type Error = S::Error;
+}
+
+impl<S: Read, F: FnMut(&[u8])> Read for Tap<S, F> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let len = self.inner.read(buf).await?;
+        (self.sink)(&buf[..len]);
+
+        Ok(len)
+    }
+}
+
+impl<S: Write, F: FnMut(&[u8])> Write for Tap<S, F> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let len = self.inner.write(buf).await?;
+        (self.sink)(&buf[..len]);
+
+        Ok(len)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush().await
+    }
+}
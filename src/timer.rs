@@ -0,0 +1,78 @@
+//! Timer abstraction used for timeouts and delays.
+
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+use core::time::Duration;
+
+/// Asynchronous delay provider.
+#[allow(async_fn_in_trait)]
+pub trait Timer {
+    /// Wait until the duration has elapsed.
+    async fn delay(&mut self, duration: Duration);
+}
+
+/// Placeholder used when no timer has been provided.
+///
+/// This isn't a [`Timer`], so methods which wait for a duration are only available once a
+/// timer has been set. Without one, responses are awaited indefinitely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTimer;
+
+/// A [`Timer`], or [`NoTimer`].
+///
+/// Implemented for every timer and for [`NoTimer`], it can't be implemented outside this crate.
+#[allow(async_fn_in_trait)]
+pub trait MaybeTimer: sealed::Sealed {
+    /// Wait until the duration has elapsed, or forever without a timer.
+    #[doc(hidden)]
+    async fn delay_or_pending(&mut self, duration: Duration);
+}
+
+impl<T: Timer> MaybeTimer for T {
+    async fn delay_or_pending(&mut self, duration: Duration) {
+        self.delay(duration).await
+    }
+}
+
+impl MaybeTimer for NoTimer {
+    async fn delay_or_pending(&mut self, _duration: Duration) {
+        core::future::pending().await
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl<T: super::Timer> Sealed for T {}
+    impl Sealed for super::NoTimer {}
+}
+
+/// Timeout has elapsed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timeout;
+
+/// Run a future to completion, giving up once the duration has elapsed.
+///
+/// Without a timer the duration never elapses.
+pub(crate) async fn with_timeout<T: MaybeTimer, F: Future>(
+    timer: &mut T,
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Timeout> {
+    let mut future = pin!(future);
+    let mut delay = pin!(timer.delay_or_pending(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        if delay.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err(Timeout));
+        }
+
+        Poll::Pending
+    })
+    .await
+}
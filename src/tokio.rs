@@ -2,6 +2,7 @@
 
 use std::io::{Read, Write};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio_serial::SerialStream;
 
@@ -23,6 +24,21 @@ pub struct SerialTx {
     serial: Arc<Mutex<SerialStream>>,
 }
 
+impl SerialTx {
+    /// Write several frames as a single transfer.
+    ///
+    /// The frames are coalesced into one buffer and written while holding the port lock once,
+    /// which avoids the per-write latency of USB-UART bridges.
+    pub async fn write_frames(&mut self, frames: &[&[u8]]) -> Result<usize, std::io::Error> {
+        let buf = frames.concat();
+        let mut serial = self.serial.lock().await;
+
+        serial.write_all(&buf)?;
+
+        Ok(buf.len())
+    }
+}
+
 impl embedded_io_async::ErrorType for SerialTx {
     type Error = std::io::Error;
 }
@@ -33,6 +49,12 @@ impl embedded_io_async::Write for SerialTx {
 
         serial.write(buf)
     }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let mut serial = self.serial.lock().await;
+
+        serial.flush()
+    }
 }
 
 pub struct SerialRx {
@@ -50,3 +72,20 @@ impl embedded_io_async::Read for SerialRx {
         serial.read(buf)
     }
 }
+
+/// [`Timer`](crate::Timer) backed by the tokio runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+impl crate::Timer for TokioTimer {
+    async fn delay(&mut self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+impl crate::Clock for TokioTimer {
+    fn now(&self) -> Duration {
+        static START: std::sync::OnceLock<tokio::time::Instant> = std::sync::OnceLock::new();
+        START.get_or_init(tokio::time::Instant::now).elapsed()
+    }
+}
@@ -0,0 +1,124 @@
+//! Single task driver combining [`Radio`] and [`Ingress`].
+
+use crate::{
+    CommandError, Event, Frame, IngestError, Ingress, MaybeTimer, NoTimer, Radio, Response,
+};
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+use embedded_io_async::{Read, Write};
+use heapless::spsc::Queue;
+
+/// Radio module instance which drives its own receive path.
+///
+/// Owns both the [`Radio`] and [`Ingress`] halves, running the ingress alongside each operation
+/// so no separate task needs to be spawned. Ingest errors are recovered from by resynchronising
+/// on the next frame, except for [fatal](IngestError::is_fatal) errors which stop the receive
+/// path, see [`Transceiver::ingest_error`]. The receive path pauses while the module is in
+/// transparent mode, and resumes any partially received frame with the next operation, see
+/// [`Ingress::run`].
+pub struct Transceiver<'a, W, R, T = NoTimer>
+where
+    W: Write,
+    R: Read,
+{
+    radio: Radio<'a, W, T>,
+    ingress: Ingress<'a, R, T>,
+    error: Option<IngestError<R::Error>>,
+}
+
+impl<'a, W, R> Transceiver<'a, W, R>
+where
+    W: Write,
+    R: Read,
+{
+    pub fn new(
+        writer: W,
+        reader: R,
+        response_queue: &'a mut Queue<Frame<Response>, 2>,
+        event_queue: &'a mut Queue<Frame<Event>, 16>,
+    ) -> Self {
+        let (radio, ingress) = Radio::new(writer, reader, response_queue, event_queue);
+
+        Self::from_parts(radio, ingress)
+    }
+}
+
+impl<'a, W, R, T> Transceiver<'a, W, R, T>
+where
+    W: Write,
+    R: Read,
+    T: MaybeTimer,
+{
+    /// Combine an existing radio and ingress pair.
+    pub fn from_parts(radio: Radio<'a, W, T>, ingress: Ingress<'a, R, T>) -> Self {
+        Self {
+            radio,
+            ingress,
+            error: None,
+        }
+    }
+
+    /// Split back into the radio and ingress halves.
+    pub fn into_parts(self) -> (Radio<'a, W, T>, Ingress<'a, R, T>) {
+        (self.radio, self.ingress)
+    }
+
+    /// The fatal error which stopped the receive path, if any.
+    pub fn ingest_error(&self) -> Option<&IngestError<R::Error>> {
+        self.error.as_ref()
+    }
+
+    /// Run an operation against the radio while driving the receive path.
+    ///
+    /// Returns [`CommandError::IngressGone`] once the receive path has stopped, abandoning the
+    /// operation if it was still running, see [`Transceiver::ingest_error`].
+    pub async fn run<O>(
+        &mut self,
+        f: impl AsyncFnOnce(&mut Radio<'a, W, T>) -> O,
+    ) -> Result<O, CommandError<W::Error>> {
+        if self.error.is_some() {
+            return Err(CommandError::IngressGone);
+        }
+
+        match drive(f(&mut self.radio), self.ingress.run()).await {
+            Ok(output) => Ok(output),
+            Err(error) => {
+                self.error = Some(error);
+                Err(CommandError::IngressGone)
+            }
+        }
+    }
+
+    /// Poll until an event is received.
+    pub async fn poll_event(&mut self) -> Result<Frame<Event>, CommandError<W::Error>> {
+        self.run(async |radio| radio.poll_event().await).await
+    }
+
+    /// Send data command.
+    ///
+    /// See [`Radio::send`].
+    pub async fn send(&mut self, data: &[u8]) -> Result<(), CommandError<W::Error>> {
+        self.run(async |radio| radio.send(data).await).await?
+    }
+}
+
+/// Poll a future to completion while also polling a background future.
+///
+/// Returns the output of the background future instead if it completes first.
+pub(crate) async fn drive<F: Future, B: Future>(
+    future: F,
+    background: B,
+) -> Result<F::Output, B::Output> {
+    let mut future = pin!(future);
+    let mut background = pin!(background);
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+
+        background.as_mut().poll(cx).map(Err)
+    })
+    .await
+}